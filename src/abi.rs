@@ -0,0 +1,119 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{fs, str};
+
+use cita_types::Address;
+use ethabi::param_type::Reader;
+use ethabi::token::{LenientTokenizer, Tokenizer};
+use ethabi::{Function, Param};
+use rustc_hex::FromHex;
+
+/// An address in hex, the 0x-prefix is optional.
+#[derive(Debug, Clone, Copy)]
+pub struct HexAddress(pub Address);
+
+impl str::FromStr for HexAddress {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = if s.starts_with("0x") { &s[2..] } else { s };
+        Address::from_str(s)
+            .map(HexAddress)
+            .map_err(|err| format!("the address [{}] is malformed: {}", s, err))
+    }
+}
+
+/// Loads the bytecode of a contract from a file in hex.
+pub fn load_bytecode(path: &str) -> Result<Vec<u8>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read the bytecode file {}: {}", path, err))?;
+    let content = content.trim();
+    let content = if content.starts_with("0x") {
+        &content[2..]
+    } else {
+        content
+    };
+    content
+        .from_hex()
+        .map_err(|err| format!("the bytecode in {} is malformed: {}", path, err))
+}
+
+/// Encodes a call by the function signature, such as `transfer(address,uint256)`.
+pub fn encode_call(signature: &str, args: &[String]) -> Result<Vec<u8>, String> {
+    let signature = signature.trim();
+    let open = signature.find('(');
+    if open.is_none() || !signature.ends_with(')') {
+        return Err(format!(
+            "the function signature [{}] is malformed",
+            signature
+        ));
+    }
+    let open = open.unwrap();
+    let name = signature[..open].trim().to_owned();
+    let inputs = split_params(&signature[open + 1..signature.len() - 1])
+        .iter()
+        .map(|param| {
+            Reader::read(param)
+                .map(|kind| Param {
+                    name: String::new(),
+                    kind,
+                })
+                .map_err(|err| format!("the type [{}] is unknown: {}", param, err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if inputs.len() != args.len() {
+        return Err(format!(
+            "the function [{}] requires {} arguments but {} are given",
+            signature,
+            inputs.len(),
+            args.len()
+        ));
+    }
+    let tokens = inputs
+        .iter()
+        .zip(args.iter())
+        .map(|(param, arg)| {
+            LenientTokenizer::tokenize(&param.kind, arg)
+                .map_err(|err| format!("the argument [{}] is malformed: {}", arg, err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let function = Function {
+        name,
+        inputs,
+        outputs: Vec::new(),
+        constant: false,
+    };
+    function
+        .encode_input(&tokens)
+        .map_err(|err| format!("failed to encode the call: {}", err))
+}
+
+// Splits parameters by the commas which are not in arrays or tuples.
+fn split_params(params: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in params.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(current.trim().to_owned());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        result.push(current.trim().to_owned());
+    }
+    result
+}
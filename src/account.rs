@@ -0,0 +1,78 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::{fmt, fs, str};
+
+use cita_crypto::{pubkey_to_address, CreateKey, KeyPair, PrivKey};
+use cita_types::Address;
+
+pub const DEFAULT_KEY: &str = "0x1000000000000000000000000000000000000000000000000000000000000000";
+
+/// A private key of a sender, in hex with the 0x-prefix.
+#[derive(Clone)]
+pub struct PrivateKey(PrivKey);
+
+impl str::FromStr for PrivateKey {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if !s.starts_with("0x") {
+            return Err("please use 0x-prefix for private key".to_owned());
+        }
+        PrivKey::from_str(&s[2..])
+            .map(PrivateKey)
+            .map_err(|err| format!("failed to parse private key: {}", err))
+    }
+}
+
+// Never print the private key.
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrivateKey({:?})", self.address())
+    }
+}
+
+impl PrivateKey {
+    pub fn generate() -> Self {
+        PrivateKey(*KeyPair::gen_keypair().privkey())
+    }
+
+    pub fn privkey(&self) -> &PrivKey {
+        &self.0
+    }
+
+    pub fn keypair(&self) -> KeyPair {
+        KeyPair::from_privkey(self.0)
+            .map_err(|err| panic!("failed to load private key to keypair: {}", err))
+            .unwrap()
+    }
+
+    pub fn address(&self) -> Address {
+        pubkey_to_address(self.keypair().pubkey())
+    }
+}
+
+/// Loads private keys from a file, one key in each line.
+///
+/// Empty lines and lines start with `#` are ignored.
+pub fn load_key_file(path: &str) -> Result<Vec<PrivateKey>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read the key file {}: {}", path, err))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(idx, line)| {
+            line.parse::<PrivateKey>()
+                .map_err(|err| format!("{}:{}: {}", path, idx + 1, err))
+        })
+        .collect()
+}
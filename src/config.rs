@@ -6,17 +6,69 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::{env, fmt, str};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use std::{env, fmt, fs, str};
 
-use clap::{App, Arg, ArgMatches};
+use cita_types::Address;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use toml::{Spanned, Value};
 
+use crate::abi::{encode_call, load_bytecode, HexAddress};
+use crate::account::{load_key_file, PrivateKey, DEFAULT_KEY};
+use crate::mission::ENGINES;
+use crate::mock::ERROR_KINDS;
+use crate::replay::{load_signed_txs, ReplayTx};
+use crate::report::OUTPUT_FORMATS;
+use crate::scenario::{load_scenario, Scenario};
+use crate::tls::TlsOptions;
 use crate::transaction::JSONRPC_METHODS;
+use crate::transport::{Connector, PROTOCOLS};
 
 const APPNAME: &str = "CITA Bench";
 const VERNUM: &str = "0.0.1";
 const LOG_LEVEL_ENV: &str = "APP_LOG_LEVEL";
 
-#[derive(Debug, Clone)]
+// Options which could be set in the configuration file.
+const CONFIG_KEYS: &[&str] = &[
+    "node",
+    "protocol",
+    "tls-ca",
+    "tls-cert",
+    "tls-key",
+    "tls-insecure",
+    "tls-sni",
+    "tls-timeout",
+    "thread",
+    "amount",
+    "interval",
+    "rate",
+    "batch",
+    "engine",
+    "max-in-flight",
+    "duration",
+    "scenario",
+    "warmup",
+    "category",
+    "mix",
+    "key",
+    "key-file",
+    "generate-keys",
+    "admin-key",
+    "replay",
+    "quota",
+    "value",
+    "bytecode",
+    "contract",
+    "function",
+    "arg",
+    "confirm-timeout",
+    "progress",
+    "output-format",
+    "output-file",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Node {
     pub host: String,
     pub port: u16,
@@ -62,34 +114,574 @@ impl fmt::Display for ParseNodeError {
     }
 }
 
+/// A category in a mixed workload, and its weight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightedCategory {
+    pub category: String,
+    pub weight: u32,
+}
+
+impl fmt::Display for WeightedCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.category, self.weight)
+    }
+}
+
+impl str::FromStr for WeightedCategory {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = s.split('=').map(str::trim).collect::<Vec<&str>>();
+        if v.len() != 2 {
+            return Err(format!("the weighted category [{}] is malformed", s));
+        }
+        if !JSONRPC_METHODS.contains(&v[0]) {
+            return Err(format!("the category [{}] is unknown", v[0]));
+        }
+        let weight = v[1]
+            .parse::<u32>()
+            .map_err(|err| format!("the weight of [{}] is malformed: {}", s, err))?;
+        if weight == 0 {
+            return Err(format!("the weight of [{}] should not be zero", s));
+        }
+        Ok(WeightedCategory {
+            category: v[0].to_owned(),
+            weight,
+        })
+    }
+}
+
 pub struct AppConfig {
     pub node: Vec<Node>,
     pub protocol: String,
+    // Only for https or wss, use the default settings if none is set.
+    pub tls: Option<TlsOptions>,
+    // Built from the protocol and the TLS options.
+    pub connector: Connector,
     pub thread: usize,
     pub amount: usize,
     pub interval: usize,
+    pub rate: usize,
+    pub batch: usize,
+    pub engine: String,
+    pub max_in_flight: usize,
+    pub duration: Option<Duration>,
+    // Follow the phases instead of the rate, the duration is the sum of them.
+    pub scenario: Option<Scenario>,
+    pub warmup: Duration,
     pub category: String,
+    // Only one item, the category, if the mix is not set.
+    pub mix: Vec<WeightedCategory>,
+    pub key: Vec<PrivateKey>,
+    // Grants the keys the permissions on a permissioned chain before the benchmark.
+    pub admin_key: Option<PrivateKey>,
+    // Send these transactions instead, the keys and the categories are ignored.
+    pub replay: Option<(String, Vec<ReplayTx>)>,
+    pub quota: u64,
+    pub value: u64,
+    pub bytecode: Option<Vec<u8>>,
+    pub contract: Option<Address>,
+    // The function selector and the encoded arguments.
+    pub call_data: Option<Vec<u8>>,
+    pub confirm_timeout: usize,
+    pub progress: usize,
+    pub output_format: String,
+    pub output_file: Option<String>,
 }
 
 impl<'a> From<&'a ArgMatches<'a>> for AppConfig {
     fn from(matches: &'a ArgMatches) -> Self {
-        let node = values_t!(matches, "node", Node).unwrap_or_else(|e| e.exit());
-        let protocol = value_t!(matches, "protocol", String).unwrap_or_else(|e| e.exit());
-        let thread = value_t!(matches, "thread", usize).unwrap_or_else(|e| e.exit());
-        let amount = value_t!(matches, "amount", usize).unwrap_or_else(|e| e.exit());
-        let interval = value_t!(matches, "interval", usize).unwrap_or_else(|e| e.exit());
-        let category = value_t!(matches, "category", String).unwrap_or_else(|e| e.exit());
+        let args = Arguments::new(matches);
+        let node = args.values_of::<Node>("node");
+        let protocol = args.value_of::<String>("protocol");
+        let tls = {
+            let options = TlsOptions {
+                ca_file: args.optional_value_of::<String>("tls-ca"),
+                client_cert: args.optional_value_of::<String>("tls-cert"),
+                client_key: args.optional_value_of::<String>("tls-key"),
+                insecure: args.is_set("tls-insecure"),
+                sni: args.optional_value_of::<String>("tls-sni"),
+                timeout: args
+                    .optional_value_of::<humantime::Duration>("tls-timeout")
+                    .map(Into::into),
+            };
+            if options.timeout == Some(Duration::new(0, 0)) {
+                exit_with_error("\"tls-timeout\" should not be zero");
+            }
+            if options.ca_file.is_some()
+                || options.client_cert.is_some()
+                || options.client_key.is_some()
+                || options.insecure
+                || options.sni.is_some()
+                || options.timeout.is_some()
+            {
+                Some(options)
+            } else {
+                None
+            }
+        };
+        let connector =
+            Connector::new(&protocol, tls.as_ref()).unwrap_or_else(|err| exit_with_error(&err));
+        let scenario = args
+            .optional_value_of::<String>("scenario")
+            .map(|path| load_scenario(&path).unwrap_or_else(|err| exit_with_error(&err)));
+        let (thread, duration) = if let Some(ref scenario) = scenario {
+            if args.is_given("duration") {
+                exit_with_error("\"duration\" is decided by the scenario");
+            }
+            if args.is_given("rate") {
+                exit_with_error("\"rate\" is decided by the scenario");
+            }
+            let thread = scenario.max_thread(args.value_of::<usize>("thread"));
+            log::info!(
+                "Start {} threads for each node, the max of the phases in {}.",
+                thread,
+                scenario.path
+            );
+            (thread, Some(scenario.duration()))
+        } else {
+            let duration = args
+                .optional_value_of::<humantime::Duration>("duration")
+                .map(Into::into);
+            (args.value_of::<usize>("thread"), duration)
+        };
+        let replay = args.optional_value_of::<String>("replay").map(|path| {
+            let txs = load_signed_txs(&path).unwrap_or_else(|err| exit_with_error(&err));
+            (path, txs)
+        });
+        // Run until the deadline or all transactions are replayed, if the amount is not set.
+        let amount = if (duration.is_some() || replay.is_some()) && !args.is_given("amount") {
+            0
+        } else {
+            args.value_of::<usize>("amount")
+        };
+        let warmup = args.value_of::<humantime::Duration>("warmup").into();
+        let interval = args.value_of::<usize>("interval");
+        // No default in the command line, since it conflicts with the scenario.
+        let rate = args.optional_value_of::<usize>("rate").unwrap_or(0);
+        let batch = args.value_of::<usize>("batch");
+        if batch == 0 {
+            exit_with_error("\"batch\" should not be zero");
+        }
+        let engine = args.value_of::<String>("engine");
+        let max_in_flight = args.value_of::<usize>("max-in-flight");
+        if max_in_flight == 0 {
+            exit_with_error("\"max-in-flight\" should not be zero");
+        }
+        let category = args.value_of::<String>("category");
+        let mix = {
+            let mix = args.optional_values_of::<WeightedCategory>("mix");
+            if mix.is_empty() {
+                vec![WeightedCategory {
+                    category: category.clone(),
+                    weight: 1,
+                }]
+            } else {
+                mix
+            }
+        };
+        let key = {
+            let mut key = args.optional_values_of::<PrivateKey>("key");
+            if let Some(path) = args.optional_value_of::<String>("key-file") {
+                key.extend(load_key_file(&path).unwrap_or_else(|err| exit_with_error(&err)));
+            }
+            let generate_keys = args.value_of::<usize>("generate-keys");
+            key.extend((0..generate_keys).map(|_| PrivateKey::generate()));
+            if key.is_empty() {
+                key.push(DEFAULT_KEY.parse().unwrap());
+            }
+            key
+        };
+        let admin_key = args.optional_value_of::<PrivateKey>("admin-key");
+        if admin_key.is_some() && replay.is_some() {
+            exit_with_error(
+                "the replayed transactions are signed already, \
+                 \"admin-key\" can not grant their senders",
+            );
+        }
+        let quota = args.value_of::<u64>("quota");
+        let value = args.value_of::<u64>("value");
+        let bytecode = args
+            .optional_value_of::<String>("bytecode")
+            .map(|path| load_bytecode(&path).unwrap_or_else(|err| exit_with_error(&err)));
+        let contract = args
+            .optional_value_of::<HexAddress>("contract")
+            .map(|addr| addr.0);
+        let call_data = args
+            .optional_value_of::<String>("function")
+            .map(|function| {
+                let arg = args.optional_values_of::<String>("arg");
+                encode_call(&function, &arg).unwrap_or_else(|err| exit_with_error(&err))
+            });
+        for item in mix.iter() {
+            match item.category.as_str() {
+                "deployContract" if bytecode.is_none() => {
+                    exit_with_error("\"bytecode\" is required to deploy a contract")
+                }
+                "callContract" | "call" if contract.is_none() || call_data.is_none() => {
+                    exit_with_error("\"contract\" and \"function\" are required to call a contract")
+                }
+                "transfer" if distinct_addresses(&key) < 2 => exit_with_error(
+                    "at least 2 keys of distinct addresses are required to transfer between them",
+                ),
+                _ => {}
+            }
+        }
+        // Pushed blocks are received one by one in each step of the sync engine.
+        if connector.is_duplex()
+            && mix.iter().any(|item| item.category == "newBlockFilter")
+            && (mix.len() > 1 || batch != 1 || engine != "sync")
+        {
+            exit_with_error(
+                "\"newBlockFilter\" subscribes to blocks over WebSocket, \
+                 it can not be mixed, batched or run by the async engine",
+            );
+        }
+        let confirm_timeout = args.value_of::<usize>("confirm-timeout");
+        let transfer = match replay {
+            Some((_, ref txs)) => txs.iter().any(|tx| tx.transfer.is_some()),
+            None => mix.iter().any(|item| item.category == "transfer"),
+        };
+        if confirm_timeout == 0 && transfer {
+            exit_with_error("transfers can not be verified without \"confirm-timeout\"");
+        }
+        let progress = args.value_of::<usize>("progress");
+        let output_format = args.value_of::<String>("output-format");
+        let output_file = args.optional_value_of::<String>("output-file");
         Self {
             node,
             protocol,
+            tls,
+            connector,
             thread,
             amount,
             interval,
+            rate,
+            batch,
+            engine,
+            max_in_flight,
+            duration,
+            scenario,
+            warmup,
             category,
+            mix,
+            key,
+            admin_key,
+            replay,
+            quota,
+            value,
+            bytecode,
+            contract,
+            call_data,
+            confirm_timeout,
+            progress,
+            output_format,
+            output_file,
+        }
+    }
+}
+
+/// Settings of the mock node.
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub listen: String,
+    pub chain_id: u64,
+    pub peer_count: u64,
+    pub latency: Duration,
+    pub error_rate: f64,
+    // Only these methods fail by the error rate, all methods if it is empty.
+    pub error_methods: Vec<String>,
+    pub error_kind: String,
+    // The code of the errors which are answered by the error rate.
+    pub error_code: i64,
+    pub block_interval: Duration,
+    pub block_size: usize,
+    // Serve https and wss instead, with the certificate and the key.
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    // Check the permissions of the senders, which are granted by the admin.
+    pub admin: Option<Address>,
+}
+
+impl<'a> From<&'a ArgMatches<'a>> for MockConfig {
+    fn from(matches: &'a ArgMatches) -> Self {
+        let listen = value_t!(matches, "listen", String).unwrap_or_else(|e| e.exit());
+        let chain_id = value_t!(matches, "chain-id", u64).unwrap_or_else(|e| e.exit());
+        let peer_count = value_t!(matches, "peer-count", u64).unwrap_or_else(|e| e.exit());
+        let latency = value_t!(matches, "latency", humantime::Duration)
+            .unwrap_or_else(|e| e.exit())
+            .into();
+        let error_rate = value_t!(matches, "error-rate", f64).unwrap_or_else(|e| e.exit());
+        if error_rate < 0.0 || error_rate > 1.0 {
+            exit_with_error("\"error-rate\" should be from 0 to 1");
+        }
+        let error_methods = values_t!(matches, "error-method", String).unwrap_or_default();
+        let error_kind = value_t!(matches, "error-kind", String).unwrap_or_else(|e| e.exit());
+        let error_code = value_t!(matches, "error-code", i64).unwrap_or_else(|e| e.exit());
+        let block_interval: Duration = value_t!(matches, "block-interval", humantime::Duration)
+            .unwrap_or_else(|e| e.exit())
+            .into();
+        if block_interval == Duration::new(0, 0) {
+            exit_with_error("\"block-interval\" should not be zero");
+        }
+        let block_size = value_t!(matches, "block-size", usize).unwrap_or_else(|e| e.exit());
+        let tls_cert = matches.value_of("tls-cert").map(str::to_owned);
+        let tls_key = matches.value_of("tls-key").map(str::to_owned);
+        if tls_cert.is_some() != tls_key.is_some() {
+            exit_with_error("both \"tls-cert\" and \"tls-key\" are required to serve TLS");
+        }
+        let admin = matches.value_of("admin").map(|admin| {
+            admin
+                .parse::<HexAddress>()
+                .unwrap_or_else(|err| exit_with_error(&err))
+                .0
+        });
+        Self {
+            listen,
+            chain_id,
+            peer_count,
+            latency,
+            error_rate,
+            error_methods,
+            error_kind,
+            error_code,
+            block_interval,
+            block_size,
+            tls_cert,
+            tls_key,
+            admin,
+        }
+    }
+}
+
+/// A TOML file with the same keys as the long names of the options.
+struct ConfigFile {
+    path: String,
+    content: String,
+    table: BTreeMap<String, Spanned<Value>>,
+}
+
+impl ConfigFile {
+    fn load(path: &str) -> Self {
+        let content = fs::read_to_string(path).unwrap_or_else(|err| {
+            exit_with_error(&format!("failed to read the config file {}: {}", path, err))
+        });
+        let table =
+            toml::from_str::<BTreeMap<String, Spanned<Value>>>(&content).unwrap_or_else(|err| {
+                let location = err
+                    .line_col()
+                    .map(|(line, col)| format!("{}:{}:{}", path, line + 1, col + 1))
+                    .unwrap_or_else(|| path.to_owned());
+                exit_with_error(&format!("{}: {}", location, err))
+            });
+        let file = Self {
+            path: path.to_owned(),
+            content,
+            table,
+        };
+        for (key, value) in file.table.iter() {
+            if !CONFIG_KEYS.contains(&key.as_str()) {
+                file.error(value, &format!("unknown option \"{}\"", key));
+            }
+        }
+        file
+    }
+
+    fn error(&self, value: &Spanned<Value>, msg: &str) -> ! {
+        let before = &self.content[..value.start()];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+        exit_with_error(&format!("{}:{}:{}: {}", self.path, line, col, msg))
+    }
+
+    fn strings_of(&self, name: &str) -> Option<(&Spanned<Value>, Vec<String>)> {
+        self.table.get(name).map(|value| {
+            let strings = match value.get_ref() {
+                Value::Array(array) => array.iter().map(|v| self.string_of(value, v)).collect(),
+                v => vec![self.string_of(value, v)],
+            };
+            (value, strings)
+        })
+    }
+
+    fn string_of(&self, spanned: &Spanned<Value>, value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            _ => self.error(spanned, "expect a string, a number or a boolean"),
+        }
+    }
+}
+
+/// Options from the command line override the same options from the file.
+struct Arguments<'a> {
+    matches: &'a ArgMatches<'a>,
+    file: Option<ConfigFile>,
+}
+
+impl<'a> Arguments<'a> {
+    fn new(matches: &'a ArgMatches<'a>) -> Self {
+        let file = matches.value_of("config").map(ConfigFile::load);
+        Self { matches, file }
+    }
+
+    fn is_given(&self, name: &str) -> bool {
+        self.matches.occurrences_of(name) != 0
+            || self
+                .file
+                .as_ref()
+                .map(|file| file.table.contains_key(name))
+                .unwrap_or(false)
+    }
+
+    // A flag in the command line, or a boolean in the file.
+    fn is_set(&self, name: &str) -> bool {
+        if self.from_file(name).is_some() {
+            self.value_of::<bool>(name)
+        } else {
+            self.matches.is_present(name)
+        }
+    }
+
+    fn from_file(&self, name: &str) -> Option<(&Spanned<Value>, Vec<String>)> {
+        if self.matches.occurrences_of(name) != 0 {
+            return None;
+        }
+        self.file.as_ref().and_then(|file| file.strings_of(name))
+    }
+
+    fn parse<T>(&self, name: &str, value: &Spanned<Value>, s: &str) -> T
+    where
+        T: str::FromStr,
+        <T as str::FromStr>::Err: fmt::Display,
+    {
+        let file = self.file.as_ref().unwrap();
+        if let Some(possible_values) = possible_values_of(name) {
+            if !possible_values.contains(&s) {
+                let msg = format!(
+                    "'{}' isn't a valid value for \"{}\" [possible values: {}]",
+                    s,
+                    name,
+                    possible_values.join(", ")
+                );
+                file.error(value, &msg);
+            }
+        }
+        s.parse::<T>().unwrap_or_else(|err| {
+            file.error(value, &format!("invalid value for \"{}\": {}", name, err))
+        })
+    }
+
+    fn value_of<T>(&self, name: &str) -> T
+    where
+        T: str::FromStr,
+        <T as str::FromStr>::Err: fmt::Display,
+    {
+        if let Some(v) = self.optional_value_of(name) {
+            v
+        } else {
+            value_t!(self.matches, name, T).unwrap_or_else(|e| e.exit())
+        }
+    }
+
+    fn optional_value_of<T>(&self, name: &str) -> Option<T>
+    where
+        T: str::FromStr,
+        <T as str::FromStr>::Err: fmt::Display,
+    {
+        if let Some((value, strings)) = self.from_file(name) {
+            if strings.len() != 1 {
+                self.file
+                    .as_ref()
+                    .unwrap()
+                    .error(value, &format!("expect a single value for \"{}\"", name));
+            }
+            Some(self.parse(name, value, &strings[0]))
+        } else if self.matches.is_present(name) {
+            Some(value_t!(self.matches, name, T).unwrap_or_else(|e| e.exit()))
+        } else {
+            None
+        }
+    }
+
+    fn values_of<T>(&self, name: &str) -> Vec<T>
+    where
+        T: str::FromStr,
+        <T as str::FromStr>::Err: fmt::Display,
+    {
+        if self.is_given(name) {
+            self.optional_values_of(name)
+        } else {
+            exit_with_error(&format!(
+                "\"{}\" is required in the command line or the config file",
+                name
+            ))
+        }
+    }
+
+    fn optional_values_of<T>(&self, name: &str) -> Vec<T>
+    where
+        T: str::FromStr,
+        <T as str::FromStr>::Err: fmt::Display,
+    {
+        if let Some((value, strings)) = self.from_file(name) {
+            // Split a string as the command line does, but keep items in an array as they are.
+            if value.get_ref().is_array() {
+                strings.iter().map(|s| self.parse(name, value, s)).collect()
+            } else {
+                strings
+                    .iter()
+                    .flat_map(|s| s.split(','))
+                    .map(|s| self.parse(name, value, s.trim()))
+                    .collect()
+            }
+        } else if self.matches.is_present(name) {
+            values_t!(self.matches, name, T).unwrap_or_else(|e| e.exit())
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl AppConfig {
+    /// The category, or the mix if it is set.
+    pub fn title(&self) -> String {
+        if let Some((ref path, _)) = self.replay {
+            format!("replay {}", path)
+        } else if self.mix.len() == 1 && self.mix[0].category == self.category {
+            self.category.clone()
+        } else {
+            self.mix
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
         }
     }
 }
 
+fn possible_values_of(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        "protocol" => Some(PROTOCOLS),
+        "category" => Some(JSONRPC_METHODS),
+        "engine" => Some(ENGINES),
+        "output-format" => Some(OUTPUT_FORMATS),
+        _ => None,
+    }
+}
+
+fn distinct_addresses(key: &[PrivateKey]) -> usize {
+    key.iter()
+        .map(PrivateKey::address)
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+pub fn exit_with_error(msg: &str) -> ! {
+    clap::Error::with_description(msg, clap::ErrorKind::InvalidValue).exit()
+}
+
 impl fmt::Display for AppConfig {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut ret = "\nAppConfig: {{\n".to_string();
@@ -97,43 +689,237 @@ impl fmt::Display for AppConfig {
         for node in self.node.iter() {
             ret.push_str(&format!("        {}\n", node));
         }
+        ret.push_str(&format!("    protocol: {}\n", self.protocol));
+        if let Some(ref tls) = self.tls {
+            ret.push_str(&format!("    tls: {:?}\n", tls));
+        }
         ret.push_str(&format!("    thread: {}\n", self.thread));
         ret.push_str(&format!("    amount: {}\n", self.amount));
         ret.push_str(&format!("    interval: {}\n", self.interval));
-        ret.push_str(&format!("    category: {}\n", self.category));
+        ret.push_str(&format!("    rate: {}\n", self.rate));
+        ret.push_str(&format!("    batch: {}\n", self.batch));
+        ret.push_str(&format!("    engine: {}\n", self.engine));
+        if self.engine == "async" {
+            ret.push_str(&format!("    max-in-flight: {}\n", self.max_in_flight));
+        }
+        if let Some(duration) = self.duration {
+            let duration = humantime::format_duration(duration);
+            ret.push_str(&format!("    duration: {}\n", duration));
+        }
+        if let Some(ref scenario) = self.scenario {
+            ret.push_str(&format!(
+                "    scenario[{}]: {}\n",
+                scenario.phases.len(),
+                scenario.path
+            ));
+            for phase in scenario.phases.iter() {
+                ret.push_str(&format!("        {:?}\n", phase));
+            }
+        }
+        let warmup = humantime::format_duration(self.warmup);
+        ret.push_str(&format!("    warmup: {}\n", warmup));
+        ret.push_str(&format!("    category: {}\n", self.title()));
+        ret.push_str(&format!("    key[{}]:\n", self.key.len()));
+        for key in self.key.iter() {
+            ret.push_str(&format!("        {:?}\n", key.address()));
+        }
+        if let Some(ref admin_key) = self.admin_key {
+            ret.push_str(&format!("    admin-key: {:?}\n", admin_key.address()));
+        }
+        ret.push_str(&format!("    quota: {}\n", self.quota));
+        ret.push_str(&format!("    value: {}\n", self.value));
+        if let Some(ref bytecode) = self.bytecode {
+            ret.push_str(&format!("    bytecode: {} bytes\n", bytecode.len()));
+        }
+        if let Some(ref contract) = self.contract {
+            ret.push_str(&format!("    contract: {:?}\n", contract));
+        }
+        if let Some(ref call_data) = self.call_data {
+            ret.push_str(&format!("    call-data: {} bytes\n", call_data.len()));
+        }
+        ret.push_str(&format!("    confirm-timeout: {}\n", self.confirm_timeout));
+        ret.push_str(&format!("    progress: {}\n", self.progress));
+        ret.push_str(&format!("    output-format: {}\n", self.output_format));
+        if let Some(ref output_file) = self.output_file {
+            ret.push_str(&format!("    output-file: {}\n", output_file));
+        }
         ret += "}}\n";
         write!(f, "{}", ret)
     }
 }
 
 pub fn build_commandline<'a>() -> App<'a, 'a> {
-    App::new(APPNAME)
+    let generate = SubCommand::with_name("generate").about(
+        "Sign transactions by the same options, and write them into the output file to replay.",
+    );
+    let mock_node = SubCommand::with_name("mock-node").about(
+        "Serve a mock node, which answers the JSON-RPC methods used by the benchmark, \
+         to test without a chain.",
+    );
+    let app = App::new(APPNAME)
         .version(VERNUM)
         .author("Boyu Yang <yangby@cryptape.com>")
         .about("Benchmark CITA.")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .takes_value(true)
+                .help(
+                    "Send the signed transactions in a file, which is generated by \"generate\".",
+                ),
+        );
+    add_options(app)
+        .subcommand(add_options(generate))
+        .subcommand(add_mock_options(mock_node))
+}
+
+fn add_mock_options<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    add_log_options(app)
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .short("l")
+                .takes_value(true)
+                .default_value("127.0.0.1:1337")
+                .help("Set the host:port to listen on."),
+        )
+        .arg(
+            Arg::with_name("chain-id")
+                .long("chain-id")
+                .takes_value(true)
+                .default_value("1")
+                .help("Set the chain id in the metadata."),
+        )
+        .arg(
+            Arg::with_name("peer-count")
+                .long("peer-count")
+                .takes_value(true)
+                .default_value("3")
+                .help("Set the number of peers to answer."),
+        )
         .arg(
-            Arg::with_name("quiet")
-                .long("quiet")
-                .short("q")
-                .conflicts_with("verbose")
-                .help("No logs printed to stdout. Only print the result."),
+            Arg::with_name("latency")
+                .long("latency")
+                .takes_value(true)
+                .default_value("0s")
+                .help("Wait for a while before answering each HTTP request, such as \"5ms\"."),
         )
         .arg(
-            Arg::with_name("verbose")
-                .long("verbose")
-                .short("v")
+            Arg::with_name("error-rate")
+                .long("error-rate")
+                .takes_value(true)
+                .default_value("0")
+                .help("Answer each call with an error in this probability, from 0 to 1."),
+        )
+        .arg(
+            Arg::with_name("error-method")
+                .long("error-method")
+                .takes_value(true)
                 .multiple(true)
+                .value_delimiter(",")
+                .help(
+                    "Only answer calls of these methods with errors, such as \
+                     \"sendRawTransaction\", so the calls to set up never fail.",
+                ),
+        )
+        .arg(
+            Arg::with_name("error-kind")
+                .long("error-kind")
+                .takes_value(true)
+                .possible_values(ERROR_KINDS)
+                .default_value(ERROR_KINDS[0])
+                .help(
+                    "Fail the calls with an error of the error code, or answer the whole request \
+                     with a malformed response.",
+                ),
+        )
+        .arg(
+            Arg::with_name("error-code")
+                .long("error-code")
+                .takes_value(true)
+                .allow_hyphen_values(true)
+                .default_value("-32603")
+                .help(
+                    "Set the code of the errors, such as -32006 to reject the transactions. \
+                     It is an internal error by default.",
+                ),
+        )
+        .arg(
+            Arg::with_name("block-interval")
+                .long("block-interval")
+                .takes_value(true)
+                .default_value("3s")
+                .help("Produce a block every interval."),
+        )
+        .arg(
+            Arg::with_name("block-size")
+                .long("block-size")
+                .takes_value(true)
+                .default_value("10000")
+                .help("Set the max number of transactions in a block."),
+        )
+        .arg(
+            Arg::with_name("tls-cert")
+                .long("tls-cert")
+                .takes_value(true)
+                .help("Serve https and wss with the certificate chain in the PEM file."),
+        )
+        .arg(
+            Arg::with_name("tls-key")
+                .long("tls-key")
+                .takes_value(true)
+                .help("Set the PEM file of the private key for the certificate."),
+        )
+        .arg(
+            Arg::with_name("admin")
+                .long("admin")
+                .takes_value(true)
+                .help(
+                    "Set the address of the admin, then only the admin and the accounts which \
+                     are granted by it can send transactions and create contracts.",
+                ),
+        )
+}
+
+fn add_log_options<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    app.arg(
+        Arg::with_name("quiet")
+            .long("quiet")
+            .short("q")
+            .conflicts_with("verbose")
+            .help("No logs printed to stdout. Only print the result."),
+    )
+    .arg(
+        Arg::with_name("verbose")
+            .long("verbose")
+            .short("v")
+            .multiple(true)
+            .help(
+                "Use verbose [Warn] output \
+                 (support -vv [Info] / -vvv [Debug] / -vvvv [Trace] \
+                 / -vvvvv.. [More Logs]).",
+            ),
+    )
+}
+
+fn add_options<'a>(app: App<'a, 'a>) -> App<'a, 'a> {
+    add_log_options(app)
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .short("C")
+                .takes_value(true)
                 .help(
-                    "Use verbose [Warn] output \
-                     (support -vv [Info] / -vvv [Debug] / -vvvv [Trace] \
-                     / -vvvvv.. [More Logs]).",
+                    "Load options from a TOML file, the keys are the long names of options. \
+                 The options in the command line override the same options in the file.",
                 ),
         )
         .arg(
             Arg::with_name("node")
                 .long("node")
                 .short("N")
-                .required(true)
+                .required_unless("config")
                 .takes_value(true)
                 .multiple(true)
                 .value_delimiter(",")
@@ -144,11 +930,48 @@ pub fn build_commandline<'a>() -> App<'a, 'a> {
                 .long("protocol")
                 .short("p")
                 .takes_value(true)
-                .possible_value("http")
-                .possible_value("https")
-                .default_value("http")
+                .possible_values(PROTOCOLS)
+                .default_value(PROTOCOLS[0])
                 .help("Set the protocol."),
         )
+        .arg(
+            Arg::with_name("tls-ca")
+                .long("tls-ca")
+                .takes_value(true)
+                .help("Trust the CA certificates in the PEM file instead of the built-in roots."),
+        )
+        .arg(
+            Arg::with_name("tls-cert")
+                .long("tls-cert")
+                .takes_value(true)
+                .help("Set the PEM file of the client certificate."),
+        )
+        .arg(
+            Arg::with_name("tls-key")
+                .long("tls-key")
+                .takes_value(true)
+                .help("Set the PEM file of the private key for the client certificate."),
+        )
+        .arg(
+            Arg::with_name("tls-insecure")
+                .long("tls-insecure")
+                .help("Do not verify the certificates of nodes, only for testing."),
+        )
+        .arg(
+            Arg::with_name("tls-sni")
+                .long("tls-sni")
+                .takes_value(true)
+                .help("Set the server name to send and verify, instead of the host of the node."),
+        )
+        .arg(
+            Arg::with_name("tls-timeout")
+                .long("tls-timeout")
+                .takes_value(true)
+                .help(
+                    "Wait up to a while, such as \"5s\", for each response over TLS. \
+                     It is 30s if it is not set.",
+                ),
+        )
         .arg(
             Arg::with_name("thread")
                 .long("thread")
@@ -173,6 +996,70 @@ pub fn build_commandline<'a>() -> App<'a, 'a> {
                 .default_value("1000")
                 .help("Wait interval millisecond between sending each request. 0 means no wait."),
         )
+        .arg(
+            Arg::with_name("rate")
+                .long("rate")
+                .short("r")
+                .takes_value(true)
+                .help(
+                    "Send requests at a fixed rate (req/s) in total of all nodes and threads, \
+                 the interval is ignored. No fixed rate if it is not set or 0.",
+                ),
+        )
+        .arg(
+            Arg::with_name("batch")
+                .long("batch")
+                .short("b")
+                .takes_value(true)
+                .default_value("1")
+                .help("Send N calls in one JSON-RPC batch. 1 means no batch."),
+        )
+        .arg(
+            Arg::with_name("engine")
+                .long("engine")
+                .takes_value(true)
+                .possible_values(ENGINES)
+                .default_value(ENGINES[0])
+                .help(
+                    "Set the engine. With \"async\", a thread for each core drives many soldiers \
+                     and keeps many requests on the way instead of waiting for each response.",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-in-flight")
+                .long("max-in-flight")
+                .takes_value(true)
+                .default_value("1000")
+                .help("Set the max number of requests on the way in all, for the async engine."),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .short("d")
+                .takes_value(true)
+                .help(
+                    "Stop all threads at the same deadline, such as \"30s\" or \"10m\". \
+                 The amount is infinite unless it is set.",
+                ),
+        )
+        .arg(
+            Arg::with_name("scenario")
+                .long("scenario")
+                .takes_value(true)
+                .conflicts_with_all(&["rate", "duration"])
+                .help(
+                    "Follow the phases in a TOML file, each one changes the rate and the number \
+                 of active threads. The thread is the max of them.",
+                ),
+        )
+        .arg(
+            Arg::with_name("warmup")
+                .long("warmup")
+                .short("w")
+                .takes_value(true)
+                .default_value("0s")
+                .help("Exclude the samples in the warmup, such as \"30s\", from the report."),
+        )
         .arg(
             Arg::with_name("category")
                 .long("category")
@@ -182,6 +1069,129 @@ pub fn build_commandline<'a>() -> App<'a, 'a> {
                 .default_value(JSONRPC_METHODS[0])
                 .help("Set the category of messages to send."),
         )
+        .arg(
+            Arg::with_name("mix")
+                .long("mix")
+                .short("m")
+                .takes_value(true)
+                .multiple(true)
+                .value_delimiter(",")
+                .help(
+                    "Mix categories by weights, such as \"sendRawTransaction=70,blockNumber=30\". \
+                 It overrides the category.",
+                ),
+        )
+        .arg(
+            Arg::with_name("key")
+                .long("key")
+                .short("k")
+                .takes_value(true)
+                .multiple(true)
+                .value_delimiter(",")
+                .help("Set the private keys (with 0x-prefix) of senders."),
+        )
+        .arg(
+            Arg::with_name("key-file")
+                .long("key-file")
+                .short("K")
+                .takes_value(true)
+                .help("Load private keys of senders from a file, one key in each line."),
+        )
+        .arg(
+            Arg::with_name("generate-keys")
+                .long("generate-keys")
+                .short("g")
+                .takes_value(true)
+                .default_value("0")
+                .help("Generate N random keys as senders."),
+        )
+        .arg(
+            Arg::with_name("admin-key")
+                .long("admin-key")
+                .takes_value(true)
+                .help(
+                    "Set the private key (with 0x-prefix) of an admin on a permissioned chain, \
+                     to grant the senders the permissions to send transactions and to create \
+                     contracts before the benchmark.",
+                ),
+        )
+        .arg(
+            Arg::with_name("quota")
+                .long("quota")
+                .takes_value(true)
+                .default_value("1000000")
+                .help("Set the quota of each transaction."),
+        )
+        .arg(
+            Arg::with_name("value")
+                .long("value")
+                .takes_value(true)
+                .default_value("1")
+                .help("Set the value of each transfer."),
+        )
+        .arg(
+            Arg::with_name("bytecode")
+                .long("bytecode")
+                .takes_value(true)
+                .help("Load the bytecode in hex from a file, to deploy a contract."),
+        )
+        .arg(
+            Arg::with_name("contract")
+                .long("contract")
+                .takes_value(true)
+                .help("Set the address of the contract to call."),
+        )
+        .arg(
+            Arg::with_name("function")
+                .long("function")
+                .takes_value(true)
+                .help("Set the signature of the function to call, such as \"transfer(uint256)\"."),
+        )
+        .arg(
+            Arg::with_name("arg")
+                .long("arg")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Set an argument of the function to call, repeat it for each argument."),
+        )
+        .arg(
+            Arg::with_name("confirm-timeout")
+                .long("confirm-timeout")
+                .takes_value(true)
+                .default_value("60")
+                .help(
+                    "Wait seconds for the sent transactions to be committed after sending. \
+                 0 means not to track transactions.",
+                ),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .short("P")
+                .takes_value(true)
+                .default_value("0")
+                .help(
+                    "Print the progress into stderr every N seconds and when Ctrl+C is pressed. \
+                 0 means no progress.",
+                ),
+        )
+        .arg(
+            Arg::with_name("output-format")
+                .long("output-format")
+                .short("f")
+                .takes_value(true)
+                .possible_values(OUTPUT_FORMATS)
+                .default_value(OUTPUT_FORMATS[0])
+                .help("Set the format of the report."),
+        )
+        .arg(
+            Arg::with_name("output-file")
+                .long("output-file")
+                .short("o")
+                .takes_value(true)
+                .help("Write the report into a file instead of stdout."),
+        )
 }
 
 fn progname() -> String {
@@ -218,7 +1228,12 @@ fn init_logger(matches: &ArgMatches) {
     pretty_env_logger::try_init_timed_custom_env(LOG_LEVEL_ENV).unwrap();
 }
 
-pub fn parse_arguments(matches: ArgMatches) -> AppConfig {
-    init_logger(&matches);
-    AppConfig::from(&matches)
+pub fn parse_arguments<'a>(matches: &'a ArgMatches<'a>) -> AppConfig {
+    init_logger(matches);
+    AppConfig::from(matches)
+}
+
+pub fn parse_mock_arguments<'a>(matches: &'a ArgMatches<'a>) -> MockConfig {
+    init_logger(matches);
+    MockConfig::from(matches)
 }
@@ -7,28 +7,58 @@
 // except according to those terms.
 
 use std::boxed::Box;
-use std::fmt;
 use std::marker::{Send, Sync};
 use std::ops::Fn;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Barrier, Mutex, RwLock};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use std::{cmp, fmt};
 
 use crate::config::{AppConfig, Node};
 use crate::report::{CaptainReport, GeneralReport, SimpleReport, SoldierReport};
+use crate::scenario::Phase;
+
+/// How the soldiers carry out the mission, the closures are shared by all threads.
+pub enum Doing<T> {
+    /// Each soldier works in a thread of its own.
+    Alone(Box<Fn(&Node, &T) -> SimpleReport + Send + Sync>),
+    /// The soldiers are spread over the reactor threads, each reactor drives its soldiers
+    /// together.
+    ///
+    /// It is called with the id of the reactor and the node of each soldier, and returns
+    /// the report of each soldier in the same order.
+    Together(
+        usize,
+        Box<Fn(usize, &[Node], &T) -> Vec<SimpleReport> + Send + Sync>,
+    ),
+}
 
 pub struct Mission<T> {
     pub data: T,
-    pub doing: Box<Fn(&Node, &T) -> SimpleReport>,
+    pub doing: Doing<T>,
 }
 
-unsafe impl<T> Send for Mission<T> {}
-unsafe impl<T> Sync for Mission<T> {}
-
 impl<T> Mission<T> {
     pub fn start(&self, node: &Node) -> SimpleReport {
-        (self.doing)(node, &self.data)
+        match self.doing {
+            Doing::Alone(ref doing) => doing(node, &self.data),
+            Doing::Together(_, ref doing) => doing(0, &[node.clone()], &self.data).remove(0),
+        }
+    }
+
+    fn reactors(&self) -> Option<usize> {
+        match self.doing {
+            Doing::Alone(_) => None,
+            Doing::Together(reactors, _) => Some(reactors),
+        }
+    }
+
+    fn drive(&self, reactor_id: usize, node: &[Node]) -> Vec<SimpleReport> {
+        match self.doing {
+            Doing::Alone(ref doing) => node.iter().map(|n| doing(n, &self.data)).collect(),
+            Doing::Together(_, ref doing) => doing(reactor_id, node, &self.data),
+        }
     }
 }
 
@@ -45,6 +75,9 @@ where
 {
     mission: Mission<T>,
     category: String,
+    warmup: Duration,
+    // The report is split by them, only when a scenario is followed.
+    phases: Vec<Phase>,
     node: Vec<Node>,
     captain_num: usize, // how many teams
     soldier_num: usize, // the size of team
@@ -59,7 +92,13 @@ where
         let soldier_num = c.thread;
         Self {
             mission: m,
-            category: c.category.clone(),
+            category: c.title(),
+            warmup: c.warmup,
+            phases: c
+                .scenario
+                .as_ref()
+                .map(|scenario| scenario.phases.clone())
+                .unwrap_or_default(),
             node: c.node.clone(),
             captain_num,
             soldier_num,
@@ -69,6 +108,18 @@ where
     fn run(&self, id: usize) -> SimpleReport {
         self.mission.start(&self.node[id].clone())
     }
+
+    // The captain id and the soldier id of the soldiers in each reactor, they take turns.
+    fn assign_reactors(&self, reactors: usize) -> Vec<Vec<(usize, usize)>> {
+        let mut assigned = vec![Vec::new(); reactors];
+        for soldier_id in 0..self.soldier_num {
+            for captain_id in 0..self.captain_num {
+                let idx = soldier_id * self.captain_num + captain_id;
+                assigned[idx % reactors].push((captain_id, soldier_id));
+            }
+        }
+        assigned
+    }
 }
 
 pub fn generate_report<T: 'static>(config: AppConfig, mission: Mission<T>) -> GeneralReport
@@ -85,6 +136,10 @@ where
     T: Send + Sync,
 {
     log::debug!("General has accepted the mission ...");
+    let reactors = { task.read().unwrap().mission.reactors() };
+    if let Some(reactors) = reactors {
+        return assign_task_to_reactors(task, reactors);
+    }
     let countdown_max = {
         let task = task.read().unwrap();
         task.captain_num * task.soldier_num + 1
@@ -92,11 +147,21 @@ where
     let countdown = Arc::new(Barrier::new(countdown_max));
     let now = Instant::now();
     let mut captain_team = vec![];
-    let (category, captain_num) = {
+    let (category, warmup, phases, captain_num) = {
         let task = task.read().unwrap();
-        (task.category.clone(), task.captain_num)
+        (
+            task.category.clone(),
+            task.warmup,
+            task.phases.clone(),
+            task.captain_num,
+        )
     };
     let report = Arc::new(Mutex::new(GeneralReport::new(category, captain_num)));
+    {
+        let mut report = report.lock().unwrap();
+        report.warmup_tm = warmup;
+        report.phases = phases;
+    }
     {
         for n in task.read().unwrap().node.iter() {
             report.lock().unwrap().node.push(n.clone())
@@ -128,6 +193,107 @@ where
     report_original
 }
 
+// Soldiers are driven by the reactors instead of captains, the reports are still split by
+// the captains.
+fn assign_task_to_reactors<T: 'static>(
+    task: Arc<RwLock<FullMission<T>>>,
+    reactors: usize,
+) -> GeneralReport
+where
+    T: Send + Sync,
+{
+    let (category, warmup, phases, captain_num, soldier_num, assigned) = {
+        let task = task.read().unwrap();
+        (
+            task.category.clone(),
+            task.warmup,
+            task.phases.clone(),
+            task.captain_num,
+            task.soldier_num,
+            task.assign_reactors(reactors),
+        )
+    };
+    let countdown = Arc::new(Barrier::new(reactors + 1));
+    let now = Instant::now();
+    let (tx, rx): (Sender<ReactorReport>, Receiver<ReactorReport>) = channel();
+    for (reactor_id, soldiers) in assigned.into_iter().enumerate() {
+        let task = task.clone();
+        let sender = tx.clone();
+        let countdown = countdown.clone();
+        thread::spawn(move || {
+            assign_task_to_reactor(task, sender, reactor_id, soldiers, countdown);
+        });
+    }
+    // Only reactors hold the senders, so a reactor which has panicked is not waited for.
+    drop(tx);
+    let mut report = GeneralReport::new(category, captain_num);
+    report.warmup_tm = warmup;
+    report.phases = phases;
+    report.node = task.read().unwrap().node.clone();
+    {
+        log::trace!("General is waiting for all reactors to be ready.");
+        countdown.wait();
+    }
+    report.ready_tm = now.elapsed();
+    log::debug!("Reactors are ready to carry out their task.");
+    let mut captain_report = (0..captain_num)
+        .map(|captain_id| CaptainReport::new(captain_id, soldier_num))
+        .collect::<Vec<_>>();
+    for (soldiers, ready_tm, cost_tm) in rx.iter() {
+        for (captain_id, soldier_report) in soldiers {
+            let subreport = &mut captain_report[captain_id];
+            subreport.ready_tm = cmp::max(subreport.ready_tm, ready_tm);
+            subreport.cost_tm = cmp::max(subreport.cost_tm, cost_tm);
+            subreport.soldier_report.push(soldier_report);
+        }
+    }
+    report.captain_report = captain_report;
+    report.cost_tm = now.elapsed() - report.ready_tm;
+    log::debug!("General has finished his task.");
+    report
+}
+
+// The reports of the soldiers with their captain ids, when the reactor was ready and done.
+type ReactorReport = (Vec<(usize, SoldierReport)>, Duration, Duration);
+
+fn assign_task_to_reactor<T>(
+    task: Arc<RwLock<FullMission<T>>>,
+    sender: Sender<ReactorReport>,
+    reactor_id: usize,
+    soldiers: Vec<(usize, usize)>,
+    countdown: Arc<Barrier>,
+) where
+    T: Send + Sync,
+{
+    log::trace!(
+        "Reactor#{} has accepted {} soldiers ...",
+        reactor_id,
+        soldiers.len()
+    );
+    let now = Instant::now();
+    countdown.wait();
+    let ready_tm = now.elapsed();
+    let results = {
+        let task = task.read().unwrap();
+        let node = soldiers
+            .iter()
+            .map(|(captain_id, _)| task.node[*captain_id].clone())
+            .collect::<Vec<_>>();
+        task.mission.drive(reactor_id, &node)
+    };
+    let cost_tm = now.elapsed();
+    let soldiers = soldiers
+        .into_iter()
+        .zip(results)
+        .map(|((captain_id, soldier_id), result)| {
+            let soldier_report = SoldierReport::new(soldier_id, ready_tm, cost_tm, result);
+            (captain_id, soldier_report)
+        })
+        .collect();
+    sender.send((soldiers, ready_tm, cost_tm)).unwrap();
+    log::trace!("Reactor#{} has finished its task.", reactor_id);
+}
+
 fn assign_task_to_captain<T: 'static>(
     task: Arc<RwLock<FullMission<T>>>,
     report: Arc<Mutex<GeneralReport>>,
@@ -149,10 +315,19 @@ fn assign_task_to_captain<T: 'static>(
             assign_task_to_soldier(task, sender, captain_id, soldier_id, countdown);
         });
     }
+    // Only soldiers hold the senders, so a soldier who has panicked is not waited for.
+    drop(tx);
     let mut subreport = CaptainReport::new(captain_id, soldier_num);
     subreport.ready_tm = now.elapsed();
-    for _ in 0..soldier_num {
-        subreport.soldier_report.push(rx.recv().unwrap());
+    for soldier_report in rx.iter() {
+        subreport.soldier_report.push(soldier_report);
+    }
+    if subreport.soldier_report.len() < soldier_num {
+        log::error!(
+            "Captain#{} lost {} soldiers who have panicked.",
+            captain_id,
+            soldier_num - subreport.soldier_report.len()
+        );
     }
     subreport.cost_tm = now.elapsed();
     {
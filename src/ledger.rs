@@ -0,0 +1,107 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeMap;
+
+use cita_types::{Address, H256, U256};
+
+use crate::report::{BalanceReport, BalanceStat};
+use crate::transaction::{fetch_balance, fetch_quota_price, fetch_quota_used};
+use crate::transport::Web3Client;
+
+/// A transfer of the native token.
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+}
+
+/// Accounts the committed transfers, to verify the balances after the run.
+#[derive(Debug)]
+pub struct Ledger {
+    before: BTreeMap<Address, U256>,
+    sent: BTreeMap<Address, U256>,
+    received: BTreeMap<Address, U256>,
+    // The senders and the hashes of the committed transfers, to charge their fees.
+    hashes: Vec<(Address, H256)>,
+}
+
+impl Ledger {
+    /// Records the balances before any transfer is sent.
+    pub fn open(web3: &Web3Client, addresses: &[Address]) -> Result<Self, String> {
+        let before = addresses
+            .iter()
+            .map(|address| {
+                fetch_balance(web3, *address)
+                    .map(|balance| (*address, balance))
+                    .map_err(|err| format!("failed to fetch the balance of {:?}: {}", address, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            before,
+            sent: BTreeMap::new(),
+            received: BTreeMap::new(),
+            hashes: Vec::new(),
+        })
+    }
+
+    pub fn commit(&mut self, hash: H256, transfer: &Transfer) {
+        *self.sent.entry(transfer.from).or_insert_with(U256::zero) += transfer.value;
+        *self.received.entry(transfer.to).or_insert_with(U256::zero) += transfer.value;
+        self.hashes.push((transfer.from, hash));
+    }
+
+    /// Sums the fees of the committed transfers by senders, none if any of them is unknown.
+    fn charge(&self, web3: &Web3Client) -> Option<BTreeMap<Address, U256>> {
+        let price = fetch_quota_price(web3)
+            .map_err(|err| log::warn!("Failed to fetch the quota price: {}", err))
+            .ok()?;
+        let mut fees = BTreeMap::new();
+        if price.is_zero() {
+            return Some(fees);
+        }
+        for (from, hash) in self.hashes.iter() {
+            let quota_used = fetch_quota_used(web3, *hash)
+                .map_err(|err| log::warn!("Failed to fetch the receipt of {:?}: {}", hash, err))
+                .ok()?;
+            *fees.entry(*from).or_insert_with(U256::zero) += quota_used * price;
+        }
+        Some(fees)
+    }
+
+    /// Compares the balances now with the ones expected by the committed transfers and fees.
+    pub fn verify(&self, web3: &Web3Client) -> BalanceReport {
+        let fees = self.charge(web3);
+        let accounts = self
+            .before
+            .iter()
+            .map(|(address, before)| {
+                let after = fetch_balance(web3, *address)
+                    .map_err(|err| {
+                        log::warn!("Failed to fetch the balance of {:?}: {}", address, err)
+                    })
+                    .ok();
+                BalanceStat {
+                    address: *address,
+                    before: *before,
+                    sent: self.sent.get(address).cloned().unwrap_or_default(),
+                    received: self.received.get(address).cloned().unwrap_or_default(),
+                    fee: fees
+                        .as_ref()
+                        .map(|fees| fees.get(address).cloned().unwrap_or_default()),
+                    after,
+                }
+            })
+            .collect();
+        BalanceReport {
+            committed: self.hashes.len(),
+            accounts,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Benchmark CITA, and embed the same scheduling and reports into other harnesses.
+
+#[macro_use]
+extern crate clap;
+
+mod abi;
+pub mod account;
+pub mod config;
+pub mod execute;
+mod ledger;
+pub mod mission;
+pub mod mock;
+mod observer;
+mod permission;
+mod progress;
+pub mod replay;
+pub mod report;
+mod sample;
+pub mod scenario;
+mod tls;
+pub mod transaction;
+pub mod transport;
+pub mod workload;
+
+pub use crate::config::AppConfig;
+pub use crate::execute::{generate_report, Doing, Mission};
+pub use crate::mission::{carry_out, generate_mission, generate_workload_mission, MissionData};
+pub use crate::report::GeneralReport;
+pub use crate::workload::{Soldier, StepOutcome, Workload};
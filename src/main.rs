@@ -6,23 +6,31 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-#[macro_use]
-extern crate clap;
-
-mod config;
-mod execute;
-mod mission;
-mod report;
-mod transaction;
-
-use config::{build_commandline, parse_arguments};
-use execute::generate_report;
-use mission::generate_mission;
+use cita_bench::config::{
+    build_commandline, exit_with_error, parse_arguments, parse_mock_arguments,
+};
+use cita_bench::{carry_out, generate_mission, mock, replay};
 
 fn main() {
     let matches = build_commandline().get_matches();
-    let config = parse_arguments(matches);
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        let config = parse_arguments(matches);
+        if let Err(err) = replay::generate(&config) {
+            exit_with_error(&err);
+        }
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("mock-node") {
+        let config = parse_mock_arguments(matches);
+        mock::serve(&config);
+        return;
+    }
+    let config = parse_arguments(&matches);
+    let output_format = config.output_format.clone();
+    let output_file = config.output_file.clone();
     let mission = generate_mission(&config);
-    let report = generate_report(config, mission);
-    report.print();
+    let report = carry_out(config, mission);
+    if let Err(err) = report.output(&output_format, output_file.as_ref().map(String::as_str)) {
+        exit_with_error(&format!("failed to output the report: {}", err));
+    }
 }
@@ -6,72 +6,733 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::sync::{Arc, RwLock};
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::config::{AppConfig, Node};
-use crate::execute::Mission;
-use crate::report::SimpleReport;
-use crate::transaction::get_func_core;
+use cita_web3::web3::futures::{stream, Future, Stream};
+use tokio_core::reactor::{Core, Handle, Timeout};
+
+use crate::account::PrivateKey;
+use crate::config::{exit_with_error, AppConfig, Node};
+use crate::execute::{generate_report, Doing, Mission};
+use crate::ledger::Ledger;
+use crate::observer::Observer;
+use crate::progress::{Progress, PUSH_INTERVAL_MILLIS};
+use crate::report::{GeneralReport, SimpleReport};
+use crate::scenario::Scenario;
+use crate::transaction::{is_tx_category, send_async, send_batch, send_batch_async, Prepared};
+use crate::transport::{Connector, Web3Client};
+use crate::workload::{CategoryWorkload, Soldier, StepOutcome, Workload};
+
+pub const ENGINES: &[&str] = &["sync", "async"];
+
+// How long an idle soldier sleeps before it checks the phase again.
+const PHASE_POLL_MILLIS: u64 = 100;
+
+/// Shares the rate among all soldiers, each request takes the next slot.
+#[derive(Debug)]
+pub enum Schedule {
+    Fixed {
+        rate: u64,
+        next: AtomicUsize,
+    },
+    // Follows the rates of the phases, from the offset of the next slot.
+    Phased {
+        scenario: Arc<Scenario>,
+        next: Mutex<Duration>,
+    },
+}
+
+impl Schedule {
+    fn new(rate: usize) -> Self {
+        Schedule::Fixed {
+            rate: rate as u64,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn phased(scenario: Arc<Scenario>) -> Self {
+        Schedule::Phased {
+            scenario,
+            next: Mutex::new(Duration::new(0, 0)),
+        }
+    }
+
+    /// Returns the intended time to send the next requests, they take `n` slots.
+    ///
+    /// None is returned when the rate is not fixed in the current phase.
+    pub fn next_slots(&self, start: Instant, n: usize) -> Option<Instant> {
+        match self {
+            Schedule::Fixed { rate, next } => {
+                let slot = next.fetch_add(n, Ordering::SeqCst) as u64;
+                Some(start + Duration::from_nanos(slot * 1_000_000_000 / rate))
+            }
+            Schedule::Phased { scenario, next } => {
+                let is_rated = |offset| {
+                    scenario
+                        .phase_at(offset)
+                        .map(|(idx, _)| scenario.phases[idx].is_rated())
+                };
+                let mut next = next.lock().unwrap();
+                if is_rated(*next) == Some(false) {
+                    // Catch up with the time, there is no slot to wait for.
+                    *next = cmp::max(*next, start.elapsed());
+                    if is_rated(*next) == Some(false) {
+                        return None;
+                    }
+                }
+                // It is the end of the scenario if no phase is found.
+                let intended = start + *next;
+                for _ in 0..n {
+                    match scenario.next_slot(*next) {
+                        Some(offset) => *next = offset,
+                        None => break,
+                    }
+                }
+                Some(intended)
+            }
+        }
+    }
+}
+
+// Sleeps for a while and returns true if the soldier is not active in the current phase.
+fn idle_in_phase(scenario: Option<&Arc<Scenario>>, rank: usize, start: Instant) -> bool {
+    let (active, wait) = active_in_phase(scenario, &[rank], start);
+    if active == 0 {
+        thread::sleep(wait);
+    }
+    active == 0
+}
+
+// Counts the soldiers which are active in the current phase, and how long to wait before
+// checking again if none is.
+fn active_in_phase(
+    scenario: Option<&Arc<Scenario>>,
+    ranks: &[usize],
+    start: Instant,
+) -> (usize, Duration) {
+    let poll = Duration::from_millis(PHASE_POLL_MILLIS);
+    let scenario = match scenario {
+        Some(scenario) => scenario,
+        None => return (ranks.len(), poll),
+    };
+    match scenario.phase_at(start.elapsed()) {
+        Some((idx, offset)) => {
+            let phase = &scenario.phases[idx];
+            let active = ranks
+                .iter()
+                .filter(|rank| phase.thread.map(|thread| **rank < thread).unwrap_or(true))
+                .count();
+            (active, cmp::min(phase.duration - offset, poll))
+        }
+        None => (ranks.len(), poll),
+    }
+}
 
 #[derive(Debug)]
 pub struct MissionData {
     pub terminate: Arc<RwLock<bool>>,
-    pub protocol: String,
+    // When the first soldier starts, shared by all soldiers.
+    pub start: Mutex<Option<Instant>>,
+    pub duration: Option<Duration>,
+    pub warmup: Duration,
+    pub connector: Connector,
     pub amount: usize,
     pub interval: usize,
-    pub category: String,
+    // How many calls are sent in one JSON-RPC batch.
+    pub batch: usize,
+    // How many requests are kept on the way in all, only for the async engine.
+    pub max_in_flight: usize,
+    // The threads which drive the soldiers together, only for the async engine.
+    pub reactors: usize,
+    pub schedule: Option<Arc<Schedule>>,
+    // The report is split by the phases, only when a scenario is followed.
+    pub scenario: Option<Arc<Scenario>>,
+    pub workload: Arc<Workload>,
+    // The workload is given instead of built from the config.
+    pub custom: bool,
+    // Tracks the transactions in the outcomes, the built-in categories track them when sent.
+    pub track_steps: bool,
+    pub node: Vec<Node>,
+    // Soldiers of each node are counted in the order they start.
+    pub next_soldier: Vec<AtomicUsize>,
+    // Only break down the report by categories when they are mixed.
+    pub mixed: bool,
+    pub observer: Arc<Observer>,
+    pub progress: Option<Arc<Progress>>,
 }
 
 impl MissionData {
-    fn from(c: &AppConfig) -> Self {
+    fn started_at(&self) -> Instant {
+        *self.start.lock().unwrap().get_or_insert_with(Instant::now)
+    }
+
+    // Returns the rank of the soldier in its node, and its id in all soldiers.
+    fn assign_soldier(&self, node: &Node) -> (usize, usize) {
+        let idx = self
+            .node
+            .iter()
+            .position(|n| n == node)
+            .expect("assign a soldier for an unknown node");
+        let rank = self.next_soldier[idx].fetch_add(1, Ordering::SeqCst);
+        (rank, rank * self.node.len() + idx)
+    }
+
+    fn track(&self, node: &Node, step: &StepOutcome, sent_at: Instant) {
+        if !self.track_steps {
+            return;
+        }
+        if let Some(hash) = step.tx_hash {
+            self.observer.track_sent(node, hash, sent_at);
+        }
+    }
+
+    // Only the soldiers ranked below the threads of the phase are active in it.
+    fn is_active(&self, rank: usize, phase: Option<usize>) -> bool {
+        match (self.scenario.as_ref(), phase) {
+            (Some(scenario), Some(idx)) => scenario.phases[idx]
+                .thread
+                .map(|thread| rank < thread)
+                .unwrap_or(true),
+            _ => true,
+        }
+    }
+
+    fn phase_of(&self, start: Instant, intended: Instant) -> Option<usize> {
+        let scenario = self.scenario.as_ref()?;
+        let offset = if intended > start {
+            intended - start
+        } else {
+            Duration::new(0, 0)
+        };
+        scenario.phase_at(offset).map(|(idx, _)| idx)
+    }
+
+    /// Prepares the workload, or the categories in the config if it is not set.
+    fn new(c: &AppConfig, workload: Option<Box<Workload>>) -> Self {
         let terminate = Arc::new(RwLock::new(false));
         let terminate_clone = terminate.clone();
-        ctrlc::set_handler(move || {
+        // Only the first mission in a process handles Ctrl+C.
+        if let Err(err) = ctrlc::set_handler(move || {
             log::warn!("Stopping since Ctrl+C ...");
             *terminate_clone.write().unwrap() = true;
-        })
-        .unwrap();
+        }) {
+            log::debug!("Ctrl+C is not handled by this mission: {}", err);
+        }
+        let custom = workload.is_some();
+        let has_tx = match workload {
+            Some(ref workload) => workload.sends_transactions(),
+            None => c.replay.is_some() || c.mix.iter().any(|item| is_tx_category(&item.category)),
+        };
+        let confirm_timeout = if has_tx && c.confirm_timeout != 0 {
+            Some(Duration::from_secs(c.confirm_timeout as u64))
+        } else {
+            None
+        };
+        let ledger = if custom {
+            None
+        } else {
+            open_ledger(c).unwrap_or_else(|err| exit_with_error(&err))
+        };
+        let observer = Observer::start(&c.connector, &c.node, confirm_timeout, ledger);
+        let mut workload =
+            workload.unwrap_or_else(|| Box::new(CategoryWorkload::new(c, observer.clone())));
+        {
+            let (_eloop, web3) = c.connector.connect(&c.node[0]);
+            if let Err(err) = workload.prepare(&web3) {
+                exit_with_error(&format!("failed to prepare the workload: {}", err));
+            }
+        }
+        let mixed = workload.categories().len() > 1;
+        let scenario = c.scenario.clone().map(Arc::new);
+        let schedule = if let Some(ref scenario) = scenario {
+            Some(Arc::new(Schedule::phased(scenario.clone())))
+        } else if c.rate != 0 {
+            Some(Arc::new(Schedule::new(c.rate)))
+        } else {
+            None
+        };
+        let progress = if c.progress == 0 {
+            None
+        } else {
+            let interval = Duration::from_secs(c.progress as u64);
+            Some(Progress::start(interval, terminate.clone()))
+        };
         Self {
             terminate,
-            protocol: c.protocol.clone(),
+            start: Mutex::new(None),
+            duration: c.duration,
+            warmup: c.warmup,
+            connector: c.connector.clone(),
             amount: c.amount,
             interval: c.interval,
-            category: c.category.clone(),
+            batch: c.batch,
+            max_in_flight: c.max_in_flight,
+            reactors: reactor_count(c),
+            schedule,
+            scenario,
+            workload: Arc::from(workload),
+            custom,
+            track_steps: custom && confirm_timeout.is_some(),
+            node: c.node.clone(),
+            next_soldier: c.node.iter().map(|_| AtomicUsize::new(0)).collect(),
+            mixed,
+            observer,
+            progress,
+        }
+    }
+}
+
+// One reactor for each core, but no more than the soldiers or the requests on the way.
+fn reactor_count(c: &AppConfig) -> usize {
+    cmp::min(
+        num_cpus::get(),
+        cmp::min(c.node.len() * c.thread, c.max_in_flight),
+    )
+}
+
+// Only when transfers are sent, the accounts are the keys or the ones in the replayed transfers.
+fn open_ledger(c: &AppConfig) -> Result<Option<Ledger>, String> {
+    let addresses = match c.replay {
+        Some((_, ref txs)) => txs
+            .iter()
+            .filter_map(|tx| tx.transfer.as_ref())
+            .flat_map(|transfer| vec![transfer.from, transfer.to])
+            .collect::<Vec<_>>(),
+        None if c.mix.iter().any(|item| item.category == "transfer") => {
+            c.key.iter().map(PrivateKey::address).collect()
         }
+        None => Vec::new(),
+    };
+    if addresses.is_empty() {
+        return Ok(None);
     }
+    let (_eloop, web3) = c.connector.connect(&c.node[0]);
+    Ledger::open(&web3, &addresses).map(Some)
 }
 
 fn doing(node: &Node, data: &MissionData) -> SimpleReport {
-    let url = format!("{}://{}:{}", data.protocol, node.host, node.port);
     let amount = data.amount;
     let interval = data.interval;
     let mut count = 0;
     let mut report = SimpleReport::new();
     let wait_millis = Duration::from_millis(data.interval as u64);
-    let (_eloop, transport) = cita_web3::web3::transports::Http::new(url.as_str()).unwrap();
-    let web3 = cita_web3::web3::Web3::new(transport);
-    let func_core = get_func_core(&data.category);
+    let (_eloop, web3) = data.connector.connect(node);
+    let (rank, soldier_id) = data.assign_soldier(node);
+    let mut soldier = data.workload.init(node, soldier_id);
+    let batch = data.batch;
+    let start = data.started_at();
+    let deadline = data.duration.map(|d| start + d);
+    let warmup_end = start + data.warmup;
+    let progress = data.progress.as_ref().map(|progress| progress.sender());
+    let push_interval = Duration::from_millis(PUSH_INTERVAL_MILLIS);
+    let mut increment = SimpleReport::new();
+    let mut last_push = Instant::now();
     loop {
-        if *data.terminate.read().unwrap() || (amount != 0 && count == amount) {
+        if let Some(ref progress) = progress {
+            if last_push.elapsed() >= push_interval {
+                let rpt = ::std::mem::replace(&mut increment, SimpleReport::new());
+                let _ = progress.send(rpt);
+                last_push = Instant::now();
+            }
+        }
+        if *data.terminate.read().unwrap() || (amount != 0 && count >= amount) {
             break;
         }
-        count += 1;
-
-        let (dur, nums) = func_core(&web3);
+        if deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+            break;
+        }
+        if idle_in_phase(data.scenario.as_ref(), rank, start) {
+            continue;
+        }
+        // The last batch may be smaller, so the amount is not exceeded.
+        let size = if amount == 0 {
+            batch
+        } else {
+            cmp::min(batch, amount - count)
+        };
+        // A step is taken at once when calls are not batched.
+        let mut calls = Vec::with_capacity(size);
+        if batch != 1 {
+            for _ in 0..size {
+                match soldier.next(&web3) {
+                    Some(call) => calls.push(call),
+                    None => break,
+                }
+            }
+            if calls.is_empty() {
+                break;
+            }
+        }
+        let n = cmp::max(calls.len(), 1);
+        count += n;
 
-        report.add(dur, nums);
-        if interval != 0 {
+        let slot = data
+            .schedule
+            .as_ref()
+            .and_then(|schedule| schedule.next_slots(start, n));
+        let (intended, lag) = if let Some(intended) = slot {
+            // Measure from the intended time, so the delay of a busy soldier is counted.
+            if deadline.map(|d| intended >= d).unwrap_or(false) {
+                break;
+            }
+            let now = Instant::now();
+            if intended > now {
+                thread::sleep(intended - now);
+            }
+            let now = Instant::now();
+            let lag = if now > intended {
+                now - intended
+            } else {
+                Duration::new(0, 0)
+            };
+            (intended, lag)
+        } else {
+            (Instant::now(), Duration::new(0, 0))
+        };
+        let (dur, steps) = if batch == 1 {
+            match soldier.step(&web3) {
+                Some(step) => (step.latency, vec![step]),
+                None => break,
+            }
+        } else {
+            send_batch(&web3, calls)
+        };
+        let measured = intended >= warmup_end;
+        let phase = data.phase_of(start, intended);
+        if batch != 1 {
+            if progress.is_some() {
+                increment.add_batch(lag + dur);
+            }
+            if measured {
+                report.add_batch(lag + dur);
+            }
+        }
+        for mut step in steps {
+            step.latency += lag;
+            data.track(node, &step, intended);
+            soldier.done(&step);
+            if progress.is_some() {
+                increment.add(step.latency, step.outcome.clone());
+            }
+            if measured {
+                record(&mut report, &step, data.mixed, phase);
+            }
+        }
+        if slot.is_none() && interval != 0 {
             thread::sleep(wait_millis);
         }
     }
+    if let Some(progress) = progress {
+        let _ = progress.send(increment);
+    }
     report
 }
 
+// The latency of the batch, and the outcomes of the calls in it.
+type SentSteps = Box<Future<Item = (Duration, Vec<StepOutcome>), Error = ()>>;
+
+// A soldier which is driven by a reactor, only used in the thread of the reactor.
+struct Member<'a> {
+    node: &'a Node,
+    web3: &'a Web3Client,
+    rank: usize,
+    soldier: RefCell<Box<Soldier + 'a>>,
+    count: Cell<usize>,
+    finished: Cell<bool>,
+    // The calls which wait for the batch to be full.
+    pending: RefCell<Vec<(Instant, Prepared)>>,
+    report: RefCell<SimpleReport>,
+}
+
+impl<'a> Member<'a> {
+    fn take_pending(&self) -> Vec<(Instant, Prepared)> {
+        self.pending.borrow_mut().drain(..).collect()
+    }
+}
+
+// Drives the soldiers of a reactor on its own event loop, they share a connection to each node.
+//
+// The reactor keeps its part of `max_in_flight` requests on the way, instead of waiting for
+// each response. The soldiers take turns to build the calls.
+fn driving_async(reactor_id: usize, node: &[Node], data: &MissionData) -> Vec<SimpleReport> {
+    let amount = data.amount;
+    let batch = data.batch;
+    let max_in_flight = data.max_in_flight / data.reactors
+        + if reactor_id < data.max_in_flight % data.reactors {
+            1
+        } else {
+            0
+        };
+    let used = data
+        .node
+        .iter()
+        .filter(|n| node.contains(n))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut core = Core::new().expect("failed to create an event loop");
+    let web3 = data
+        .connector
+        .connect_all(&used, max_in_flight, &core.handle());
+    let members = node
+        .iter()
+        .map(|n| {
+            let (rank, soldier_id) = data.assign_soldier(n);
+            Member {
+                node: n,
+                web3: &web3[used.iter().position(|u| u == n).unwrap()],
+                rank,
+                soldier: RefCell::new(data.workload.init(n, soldier_id)),
+                count: Cell::new(0),
+                finished: Cell::new(false),
+                pending: RefCell::new(Vec::with_capacity(batch)),
+                report: RefCell::new(SimpleReport::new()),
+            }
+        })
+        .collect::<Vec<_>>();
+    let start = data.started_at();
+    let deadline = data.duration.map(|d| start + d);
+    let warmup_end = start + data.warmup;
+    let progress = data.progress.as_ref().map(|progress| progress.sender());
+    let push_interval = Duration::from_millis(PUSH_INTERVAL_MILLIS);
+    let mut increment = SimpleReport::new();
+    let mut last_push = Instant::now();
+    let mut cursor = 0;
+    let ticks: Box<Stream<Item = Instant, Error = ()>> =
+        if data.schedule.is_some() || data.interval != 0 {
+            let ranks = members.iter().map(|member| member.rank).collect();
+            feed_ticks(data, ranks, start, deadline, &core.handle())
+        } else {
+            Box::new(stream::repeat::<_, ()>(()).map(|_| Instant::now()))
+        };
+    // Returns the calls of a soldier when its batch is full or it has finished, or `Some(None)`
+    // if no soldier is active in the phase, or None if all soldiers have finished.
+    let mut pick = |intended: Instant| {
+        if *data.terminate.read().unwrap() || deadline.map(|d| intended >= d).unwrap_or(false) {
+            return None;
+        }
+        let phase = data.phase_of(start, intended);
+        let mut idle = false;
+        for _ in 0..members.len() {
+            let idx = cursor;
+            cursor = (cursor + 1) % members.len();
+            let member = &members[idx];
+            if member.finished.get() {
+                continue;
+            }
+            if !data.is_active(member.rank, phase) {
+                idle = true;
+                continue;
+            }
+            let call = member.soldier.borrow_mut().next(member.web3);
+            match call {
+                Some(call) => {
+                    member.count.set(member.count.get() + 1);
+                    member.pending.borrow_mut().push((intended, call));
+                    // The last batch may be smaller, so the amount is not exceeded.
+                    if amount != 0 && member.count.get() >= amount {
+                        member.finished.set(true);
+                    } else if member.pending.borrow().len() < batch {
+                        return Some(None);
+                    }
+                    return Some(Some((idx, member.take_pending())));
+                }
+                None => {
+                    member.finished.set(true);
+                    let pending = member.take_pending();
+                    if !pending.is_empty() {
+                        return Some(Some((idx, pending)));
+                    }
+                }
+            }
+        }
+        if idle {
+            Some(None)
+        } else {
+            None
+        }
+    };
+    // The calls which are not sent when it stops, since their batches are not full.
+    let rest = stream::once::<_, ()>(Ok(())).map(|()| {
+        let rest = members
+            .iter()
+            .enumerate()
+            .map(|(idx, member)| (idx, member.take_pending()))
+            .filter(|(_, pending)| !pending.is_empty())
+            .collect::<Vec<_>>();
+        stream::iter_ok(rest)
+    });
+    let driving = ticks
+        .map(pick)
+        .take_while(|item| Ok(item.is_some()))
+        .filter_map(|item| item.unwrap_or(None))
+        .chain(rest.flatten())
+        .map(|(idx, mut items)| {
+            // Measure from the intended time of the last one, when the batch is full.
+            let intended = items.last().unwrap().0;
+            let web3 = members[idx].web3;
+            let future: SentSteps = if batch == 1 {
+                let call = items.pop().unwrap().1;
+                let future = send_async(web3, call, intended);
+                Box::new(future.map(|step| (step.latency, vec![step])))
+            } else {
+                let calls = items.into_iter().map(|item| item.1).collect();
+                send_batch_async(web3, calls, intended)
+            };
+            future.map(move |(dur, steps)| (idx, intended, dur, steps))
+        })
+        .buffer_unordered(max_in_flight)
+        .for_each(|(idx, intended, dur, steps)| {
+            let member = &members[idx];
+            let mut report = member.report.borrow_mut();
+            let measured = intended >= warmup_end;
+            let phase = data.phase_of(start, intended);
+            if batch != 1 {
+                if progress.is_some() {
+                    increment.add_batch(dur);
+                }
+                if measured {
+                    report.add_batch(dur);
+                }
+            }
+            for step in steps {
+                data.track(member.node, &step, intended);
+                member.soldier.borrow_mut().done(&step);
+                if progress.is_some() {
+                    increment.add(step.latency, step.outcome.clone());
+                }
+                if measured {
+                    record(&mut report, &step, data.mixed, phase);
+                }
+            }
+            if let Some(ref progress) = progress {
+                if last_push.elapsed() >= push_interval {
+                    let rpt = ::std::mem::replace(&mut increment, SimpleReport::new());
+                    let _ = progress.send(rpt);
+                    last_push = Instant::now();
+                }
+            }
+            Ok(())
+        });
+    core.run(driving).unwrap();
+    if let Some(progress) = progress {
+        let _ = progress.send(increment);
+    }
+    members
+        .into_iter()
+        .map(|member| member.report.into_inner())
+        .collect()
+}
+
+fn record(report: &mut SimpleReport, step: &StepOutcome, mixed: bool, phase: Option<usize>) {
+    if let Some(phase) = phase {
+        report.add_in_phase(phase, step.latency, step.outcome.clone());
+    }
+    if mixed {
+        report.add_of(step.category, step.latency, step.outcome.clone());
+    } else {
+        report.add(step.latency, step.outcome.clone());
+    }
+}
+
+// Yields the intended time of each request by the schedule or the interval on the event loop,
+// so the reactor never sleeps while its requests are on the way. The soldiers which are active
+// in the phase share the interval, so each of them sends a request in each interval.
+fn feed_ticks(
+    data: &MissionData,
+    ranks: Vec<usize>,
+    start: Instant,
+    deadline: Option<Instant>,
+    handle: &Handle,
+) -> Box<Stream<Item = Instant, Error = ()>> {
+    let schedule = data.schedule.clone();
+    let scenario = data.scenario.clone();
+    let terminate = data.terminate.clone();
+    let interval = Duration::from_millis(data.interval as u64);
+    let handle = handle.clone();
+    // Carries the earliest time of the next request which takes no slot.
+    let ticks = stream::unfold(Instant::now(), move |not_before| {
+        if *terminate.read().unwrap() {
+            return None;
+        }
+        let (active, wait) = active_in_phase(scenario.as_ref(), &ranks, start);
+        let (intended, next) = if active == 0 {
+            // Check the phase again after a while.
+            (None, Instant::now() + wait)
+        } else {
+            match schedule.as_ref().and_then(|s| s.next_slots(start, 1)) {
+                Some(slot) => (Some(slot), not_before),
+                None => {
+                    let intended = cmp::max(not_before, Instant::now());
+                    (Some(intended), intended + interval / active as u32)
+                }
+            }
+        };
+        let at = intended.unwrap_or(next);
+        if deadline.map(|d| at >= d).unwrap_or(false) {
+            return None;
+        }
+        let timer = Timeout::new_at(at, &handle).expect("failed to set a timer");
+        Some(timer.then(move |_| Ok::<_, ()>((intended, next))))
+    });
+    Box::new(ticks.filter_map(|intended| intended))
+}
+
+fn select_engine(engine: &str, reactors: usize) -> Doing<MissionData> {
+    match engine {
+        "async" => Doing::Together(reactors, Box::new(driving_async)),
+        _ => Doing::Alone(Box::new(doing)),
+    }
+}
+
 pub fn generate_mission(config: &AppConfig) -> Mission<MissionData> {
-    Mission {
-        data: MissionData::from(config),
-        doing: Box::new(doing),
+    let data = MissionData::new(config, None);
+    let doing = select_engine(&config.engine, data.reactors);
+    Mission { data, doing }
+}
+
+/// Runs the workload instead of the categories and the replay in the config.
+pub fn generate_workload_mission(
+    config: &AppConfig,
+    workload: Box<Workload>,
+) -> Mission<MissionData> {
+    let data = MissionData::new(config, Some(workload));
+    let doing = select_engine(&config.engine, data.reactors);
+    Mission { data, doing }
+}
+
+/// Runs the mission, then waits for the confirmations and checks the chain.
+pub fn carry_out(config: AppConfig, mission: Mission<MissionData>) -> GeneralReport {
+    let observer = mission.data.observer.clone();
+    let progress = mission.data.progress.clone();
+    let workload = mission.data.workload.clone();
+    let connector = config.connector.clone();
+    let node = config.node[0].clone();
+    // The categories in the config are not sent by a custom workload.
+    let title = if mission.data.custom {
+        Some(workload.categories().join(","))
+    } else {
+        None
+    };
+    let mut report = generate_report(config, mission);
+    if let Some(title) = title {
+        report.title = title;
+    }
+    if let Some(progress) = progress {
+        progress.finish();
     }
+    {
+        let (_eloop, web3) = connector.connect(&node);
+        workload.finish(&web3);
+    }
+    let (confirm_report, block_report) = observer.finish();
+    report.confirm_report = confirm_report;
+    report.block_report = Some(block_report);
+    report.balance_report = observer.verify_balances();
+    report.delivery_report = observer.delivery_report();
+    report
 }
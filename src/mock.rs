@@ -0,0 +1,824 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use cita_crypto::{pubkey_to_address, PubKey};
+use cita_types::{Address, H256, U256};
+use libproto::{
+    blockchain::{SignedTransaction, UnverifiedTransaction},
+    TryFrom,
+};
+use rand::{thread_rng, Rng};
+use rustc_hex::{FromHex, ToHex};
+use rustls::{ServerConfig, ServerSession, Session};
+use serde_json::{json, Value};
+use websocket::server::upgrade::sync::IntoWs;
+use websocket::sync::Writer;
+use websocket::{OwnedMessage, WebSocketError};
+
+use crate::config::MockConfig;
+use crate::permission::{decode_grant, CREATE_CONTRACT, PERMISSION_MANAGEMENT, SEND_TX};
+use crate::tls;
+
+// The furthest block which CITA accepts for `valid_until_block`.
+const VALID_BLOCKS: u64 = 100;
+// The balance of each account before any transfer.
+const INITIAL_BALANCE: u64 = 1_000_000_000_000_000_000;
+// The version of CITA which the mock node answers as.
+const SOFTWARE_VERSION: &str = "v0.22.0";
+// A response which is cut off, so it can not be decoded.
+const MALFORMED_RESPONSE: &str = "{\"jsonrpc\":\"2.0\",\"id\":";
+// The only subscription over WebSocket is to the new blocks.
+const SUBSCRIBE_METHOD: &str = "subscribe";
+// How often the new blocks are checked to push.
+const PUSH_INTERVAL_MILLIS: u64 = 10;
+// How long to wait for the headers of a request, to tell whether it upgrades to WebSocket.
+const HEAD_TIMEOUT_MILLIS: u64 = 1000;
+
+/// How the calls fail by the error rate: with an error of the error code, or in a malformed
+/// response.
+pub const ERROR_KINDS: &[&str] = &["rpc", "malformed"];
+
+#[derive(Debug)]
+struct Block {
+    hash: H256,
+    timestamp: u64,
+    tx_hashes: Vec<H256>,
+}
+
+#[derive(Debug)]
+struct MockTx {
+    from: Address,
+    // None if it deploys a contract.
+    to: Option<Address>,
+    value: U256,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct CommittedTx {
+    height: u64,
+    index: usize,
+    from: Address,
+    // The contract which is deployed by it.
+    contract: Option<Address>,
+}
+
+#[derive(Debug)]
+enum Filter {
+    // The height which the changes are polled to.
+    Block(u64),
+    // No log is ever emitted by the mock node.
+    Log,
+}
+
+/// The chain of the mock node, blocks are produced by time.
+#[derive(Debug)]
+struct Chain {
+    // The genesis block is the first one.
+    blocks: Vec<Block>,
+    heights: HashMap<H256, u64>,
+    pool: Vec<H256>,
+    pending: HashMap<H256, MockTx>,
+    committed: HashMap<H256, CommittedTx>,
+    balances: HashMap<Address, U256>,
+    nonces: HashMap<Address, u64>,
+    code: HashMap<Address, Vec<u8>>,
+    filters: HashMap<u64, Filter>,
+    next_filter_id: u64,
+    // Only the admin and the granted accounts are permitted, if the admin is set.
+    admin: Option<Address>,
+    permissions: HashMap<Address, Vec<Address>>,
+}
+
+impl Chain {
+    fn new(admin: Option<Address>) -> Self {
+        let genesis = Block {
+            hash: random_hash(),
+            timestamp: now_millis(),
+            tx_hashes: Vec::new(),
+        };
+        let mut heights = HashMap::new();
+        heights.insert(genesis.hash, 0);
+        Self {
+            blocks: vec![genesis],
+            heights,
+            pool: Vec::new(),
+            pending: HashMap::new(),
+            committed: HashMap::new(),
+            balances: HashMap::new(),
+            nonces: HashMap::new(),
+            code: HashMap::new(),
+            filters: HashMap::new(),
+            next_filter_id: 1,
+            admin,
+            permissions: HashMap::new(),
+        }
+    }
+
+    fn height(&self) -> u64 {
+        self.blocks.len() as u64 - 1
+    }
+
+    fn balance(&self, address: &Address) -> U256 {
+        self.balances
+            .get(address)
+            .cloned()
+            .unwrap_or_else(|| INITIAL_BALANCE.into())
+    }
+
+    fn is_permitted(&self, account: &Address, permission: &str) -> bool {
+        let admin = match self.admin {
+            Some(admin) => admin,
+            None => return true,
+        };
+        let permission = permission.parse::<Address>().unwrap();
+        *account == admin
+            || self
+                .permissions
+                .get(account)
+                .map_or(false, |granted| granted.contains(&permission))
+    }
+
+    // The value is transferred only if the sender has enough balance, and only the grants of
+    // the admin take effect.
+    fn execute(&mut self, hash: H256, tx: MockTx) -> Option<Address> {
+        *self.nonces.entry(tx.from).or_insert(0) += 1;
+        if tx.to == PERMISSION_MANAGEMENT.parse::<Address>().ok() && Some(tx.from) == self.admin {
+            if let Some((account, permissions)) = decode_grant(&tx.data) {
+                let granted = self.permissions.entry(account).or_insert_with(Vec::new);
+                granted.extend(permissions);
+            }
+        }
+        let from_balance = self.balance(&tx.from);
+        let to = match tx.to {
+            Some(to) => to,
+            None => {
+                let contract = Address::from(hash);
+                self.code.insert(contract, tx.data);
+                return Some(contract);
+            }
+        };
+        if from_balance >= tx.value && tx.from != to {
+            let to_balance = self.balance(&to);
+            self.balances.insert(tx.from, from_balance - tx.value);
+            self.balances.insert(to, to_balance + tx.value);
+        }
+        None
+    }
+
+    fn produce(&mut self, size: usize) {
+        let count = ::std::cmp::min(size, self.pool.len());
+        let tx_hashes = self.pool.drain(..count).collect::<Vec<_>>();
+        let height = self.height() + 1;
+        for (index, hash) in tx_hashes.iter().enumerate() {
+            let tx = self.pending.remove(hash).unwrap();
+            let from = tx.from;
+            let contract = self.execute(*hash, tx);
+            let committed = CommittedTx {
+                height,
+                index,
+                from,
+                contract,
+            };
+            self.committed.insert(*hash, committed);
+        }
+        log::debug!(
+            "Mock node produces block#{} with {} transactions.",
+            height,
+            count
+        );
+        let hash = random_hash();
+        self.heights.insert(hash, height);
+        self.blocks.push(Block {
+            hash,
+            timestamp: now_millis(),
+            tx_hashes,
+        });
+    }
+}
+
+/// Serves the JSON-RPC methods which are used by the benchmark, until it is killed.
+pub fn serve(c: &MockConfig) {
+    let listener = bind(c);
+    let tls = tls_config(c);
+    log::info!("Mock node is listening on {}.", c.listen);
+    accept(&listener, tls, c);
+}
+
+/// Serves in the background, returns the address which it listens on.
+pub fn spawn(c: &MockConfig) -> SocketAddr {
+    let listener = bind(c);
+    let tls = tls_config(c);
+    let addr = listener.local_addr().unwrap();
+    let c = c.clone();
+    thread::spawn(move || accept(&listener, tls, &c));
+    addr
+}
+
+fn bind(c: &MockConfig) -> TcpListener {
+    TcpListener::bind(&c.listen)
+        .map_err(|err| panic!("failed to listen on {}: {}", c.listen, err))
+        .unwrap()
+}
+
+fn tls_config(c: &MockConfig) -> Option<Arc<ServerConfig>> {
+    match (&c.tls_cert, &c.tls_key) {
+        (Some(cert), Some(key)) => tls::server_config(cert, key)
+            .map(|config| Some(Arc::new(config)))
+            .map_err(|err| panic!("failed to serve TLS: {}", err))
+            .unwrap(),
+        _ => None,
+    }
+}
+
+// With TLS, each connection is decrypted into a plain one to another listener.
+fn accept(listener: &TcpListener, tls: Option<Arc<ServerConfig>>, c: &MockConfig) {
+    let config = match tls {
+        Some(config) => config,
+        None => return accept_plain(listener, c),
+    };
+    let plain = TcpListener::bind("127.0.0.1:0")
+        .map_err(|err| panic!("failed to listen for the decrypted connections: {}", err))
+        .unwrap();
+    let plain_addr = plain.local_addr().unwrap();
+    {
+        let c = c.clone();
+        thread::spawn(move || accept_plain(&plain, &c));
+    }
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let config = config.clone();
+                thread::spawn(move || {
+                    if let Err(err) = terminate_tls(stream, &config, plain_addr) {
+                        log::debug!("Mock node closes a TLS connection: {}", err);
+                    }
+                });
+            }
+            Err(err) => log::warn!("Mock node failed to accept a connection: {}", err),
+        }
+    }
+}
+
+fn accept_plain(listener: &TcpListener, c: &MockConfig) {
+    let chain = Arc::new(Mutex::new(Chain::new(c.admin)));
+    {
+        let chain = chain.clone();
+        let block_interval = c.block_interval;
+        let block_size = c.block_size;
+        thread::spawn(move || loop {
+            thread::sleep(block_interval);
+            chain.lock().unwrap().produce(block_size);
+        });
+    }
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let chain = chain.clone();
+                let c = c.clone();
+                thread::spawn(move || {
+                    let result = match is_websocket(&stream) {
+                        Ok(true) => handle_websocket(stream, &c, &chain),
+                        Ok(false) => handle_connection(stream, &c, &chain),
+                        Err(err) => Err(err),
+                    };
+                    if let Err(err) = result {
+                        log::debug!("Mock node closes a connection: {}", err);
+                    }
+                });
+            }
+            Err(err) => log::warn!("Mock node failed to accept a connection: {}", err),
+        }
+    }
+}
+
+// Decrypts the requests to the plain connection, and encrypts the responses from it.
+fn terminate_tls(
+    stream: TcpStream,
+    config: &Arc<ServerConfig>,
+    plain_addr: SocketAddr,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut plain = TcpStream::connect(plain_addr)?;
+    plain.set_nodelay(true)?;
+    let session = Arc::new(Mutex::new(ServerSession::new(config)));
+    {
+        let session = session.clone();
+        let mut reader = plain.try_clone()?;
+        let mut writer = stream.try_clone()?;
+        thread::spawn(move || -> io::Result<()> {
+            let mut buf = vec![0; 16 * 1024];
+            loop {
+                let len = reader.read(&mut buf)?;
+                let mut session = session.lock().unwrap();
+                if len == 0 {
+                    session.send_close_notify();
+                } else {
+                    session.write_all(&buf[..len])?;
+                }
+                while session.wants_write() {
+                    session.write_tls(&mut writer)?;
+                }
+                if len == 0 {
+                    return writer.shutdown(Shutdown::Write);
+                }
+            }
+        });
+    }
+    let mut reader = stream.try_clone()?;
+    let mut writer = stream;
+    let mut buf = vec![0; 16 * 1024];
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            return plain.shutdown(Shutdown::Write);
+        }
+        let mut received = &buf[..len];
+        let mut decrypted = Vec::new();
+        {
+            let mut session = session.lock().unwrap();
+            while !received.is_empty() {
+                session.read_tls(&mut received)?;
+                session
+                    .process_new_packets()
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                // An error after the peer closes the session, which is the end of the data.
+                let _ = session.read_to_end(&mut decrypted);
+            }
+            while session.wants_write() {
+                session.write_tls(&mut writer)?;
+            }
+        }
+        // Written without the session, so the responses are encrypted in the meantime.
+        plain.write_all(&decrypted)?;
+    }
+}
+
+// The request is only peeked, so it is read again by the handler.
+fn is_websocket(stream: &TcpStream) -> io::Result<bool> {
+    let mut head = [0; 4096];
+    for _ in 0..HEAD_TIMEOUT_MILLIS {
+        let len = stream.peek(&mut head)?;
+        let text = String::from_utf8_lossy(&head[..len]).to_lowercase();
+        if len == 0 || len == head.len() || text.contains("\r\n\r\n") {
+            return Ok(text.contains("upgrade: websocket"));
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    Ok(false)
+}
+
+// Serves the calls in text messages, and pushes the new blocks to the subscriptions.
+fn handle_websocket(
+    stream: TcpStream,
+    c: &MockConfig,
+    chain: &Arc<Mutex<Chain>>,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let client = stream
+        .into_ws()
+        .map_err(|(_, _, _, err)| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?
+        .accept()
+        .map_err(|(_, err)| err)?;
+    let (mut reader, writer) = client.split()?;
+    // The pushes stop once the connection is closed, as they only hold weak references.
+    let writer = Arc::new(Mutex::new(writer));
+    let mut subscriptions = 0;
+    for message in reader.incoming_messages() {
+        let text = match message.map_err(websocket_error)? {
+            OwnedMessage::Text(text) => text,
+            OwnedMessage::Ping(data) => {
+                let pong = OwnedMessage::Pong(data);
+                writer
+                    .lock()
+                    .unwrap()
+                    .send_message(&pong)
+                    .map_err(websocket_error)?;
+                continue;
+            }
+            OwnedMessage::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        if c.latency != Duration::new(0, 0) {
+            thread::sleep(c.latency);
+        }
+        let response = match serde_json::from_str::<Value>(&text) {
+            Ok(ref call) if call["method"] == SUBSCRIBE_METHOD => {
+                subscriptions += 1;
+                let id = hex_u64(subscriptions);
+                push_blocks(Arc::downgrade(&writer), id.clone(), chain.clone());
+                json!({"jsonrpc": "2.0", "id": call["id"], "result": id}).to_string()
+            }
+            Ok(request) => match handle_request(request, c, chain) {
+                Some(response) => response.to_string(),
+                None => MALFORMED_RESPONSE.to_owned(),
+            },
+            Err(err) => error(Value::Null, -32700, &err.to_string()).to_string(),
+        };
+        writer
+            .lock()
+            .unwrap()
+            .send_message(&OwnedMessage::Text(response))
+            .map_err(websocket_error)?;
+    }
+    Ok(())
+}
+
+fn push_blocks(writer: Weak<Mutex<Writer<TcpStream>>>, id: String, chain: Arc<Mutex<Chain>>) {
+    thread::spawn(move || {
+        let mut pushed = chain.lock().unwrap().height();
+        loop {
+            thread::sleep(Duration::from_millis(PUSH_INTERVAL_MILLIS));
+            let blocks = {
+                let chain = chain.lock().unwrap();
+                let blocks = (pushed + 1..=chain.height())
+                    .map(|height| block_json(height, &chain, BlockPart::Full))
+                    .collect::<Vec<_>>();
+                pushed = chain.height();
+                blocks
+            };
+            let writer = match writer.upgrade() {
+                Some(writer) => writer,
+                None => return,
+            };
+            for block in blocks {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "subscription",
+                    "params": {"subscription": id, "result": block},
+                });
+                let message = OwnedMessage::Text(notification.to_string());
+                if writer.lock().unwrap().send_message(&message).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+fn websocket_error(err: WebSocketError) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+// Serves HTTP/1.1 requests on a kept-alive connection.
+fn handle_connection(stream: TcpStream, c: &MockConfig, chain: &Mutex<Chain>) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let is_post = line.starts_with("POST ");
+        let mut content_length = 0;
+        let mut keep_alive = !line.trim_end().ends_with("HTTP/1.0");
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                break;
+            }
+            let mut parts = line.splitn(2, ':');
+            let name = parts.next().unwrap_or("").trim().to_lowercase();
+            let value = parts.next().unwrap_or("").trim().to_lowercase();
+            match name.as_str() {
+                "content-length" => content_length = value.parse::<usize>().unwrap_or(0),
+                "connection" => keep_alive = value != "close",
+                _ => {}
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        let (status, response) = if is_post {
+            if c.latency != Duration::new(0, 0) {
+                thread::sleep(c.latency);
+            }
+            match serde_json::from_slice::<Value>(&body) {
+                Ok(request) => match handle_request(request, c, chain) {
+                    Some(response) => ("200 OK", response.to_string()),
+                    None => ("200 OK", MALFORMED_RESPONSE.to_owned()),
+                },
+                Err(err) => (
+                    "200 OK",
+                    error(Value::Null, -32700, &err.to_string()).to_string(),
+                ),
+            }
+        } else {
+            ("405 Method Not Allowed", Value::Null.to_string())
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        )?;
+        writer.flush()?;
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+// None if the response is malformed, for any call in the batch.
+fn handle_request(request: Value, c: &MockConfig, chain: &Mutex<Chain>) -> Option<Value> {
+    match request {
+        Value::Array(calls) => calls
+            .into_iter()
+            .map(|call| handle_call(call, c, chain))
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array),
+        call => handle_call(call, c, chain),
+    }
+}
+
+fn handle_call(call: Value, c: &MockConfig, chain: &Mutex<Chain>) -> Option<Value> {
+    let id = call["id"].clone();
+    let method = call["method"].as_str().unwrap_or("");
+    let fails = c.error_methods.is_empty() || c.error_methods.iter().any(|m| m == method);
+    if fails && c.error_rate > 0.0 && thread_rng().gen::<f64>() < c.error_rate {
+        if c.error_kind == "malformed" {
+            return None;
+        }
+        return Some(error(id, c.error_code, "Error injected by the mock node"));
+    }
+    let params = call["params"].as_array().cloned().unwrap_or_default();
+    let mut chain = chain.lock().unwrap();
+    let result = match method {
+        "peerCount" => Ok(json!(hex_u64(c.peer_count))),
+        "blockNumber" => Ok(json!(hex_u64(chain.height()))),
+        "getMetaData" => Ok(metadata(c, &chain)),
+        "getVersion" => Ok(json!({ "softwareVersion": SOFTWARE_VERSION })),
+        "sendRawTransaction" => send_raw_transaction(&params, &mut chain),
+        "call" => Ok(json!(format!("0x{}", "0".repeat(64)))),
+        "getBlockByNumber" => height_param(&params, 0, &chain)
+            .map(|height| block_json(height, &chain, BlockPart::Full)),
+        "getBlockByHash" => hash_param(&params, 0).map(|hash| match chain.heights.get(&hash) {
+            Some(height) => block_json(*height, &chain, BlockPart::Full),
+            None => Value::Null,
+        }),
+        "getBlockHeader" => height_param(&params, 0, &chain)
+            .map(|height| block_json(height, &chain, BlockPart::Header)),
+        "getTransaction" => hash_param(&params, 0).map(|hash| transaction_json(&hash, &chain)),
+        "getTransactionReceipt" => hash_param(&params, 0).map(|hash| receipt_json(&hash, &chain)),
+        "getTransactionProof" | "getStateProof" | "getAbi" => Ok(json!("0x")),
+        "getTransactionCount" => address_param(&params, 0).map(|address| {
+            let nonce = chain.nonces.get(&address).cloned().unwrap_or(0);
+            json!(hex_u64(nonce))
+        }),
+        "getBalance" => address_param(&params, 0)
+            .map(|address| json!(format!("{:#x}", chain.balance(&address)))),
+        "getCode" => address_param(&params, 0).map(|address| {
+            let code = chain.code.get(&address).cloned().unwrap_or_default();
+            json!(format!("0x{}", code.to_hex::<String>()))
+        }),
+        "getLogs" => Ok(json!([])),
+        "newBlockFilter" => Ok(new_filter(Filter::Block(chain.height()), &mut chain)),
+        "newFilter" => Ok(new_filter(Filter::Log, &mut chain)),
+        "getFilterChanges" => {
+            filter_id_param(&params).and_then(|id| filter_changes(id, &mut chain))
+        }
+        "uninstallFilter" => {
+            filter_id_param(&params).map(|id| json!(chain.filters.remove(&id).is_some()))
+        }
+        _ => Err((-32601, "Method not found".to_owned())),
+    };
+    Some(match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err((code, message)) => error(id, code, &message),
+    })
+}
+
+type MockResult<T> = Result<T, (i64, String)>;
+
+fn invalid_params<T>() -> MockResult<T> {
+    Err((-32602, "Invalid params".to_owned()))
+}
+
+fn str_param(params: &[Value], idx: usize) -> MockResult<&str> {
+    match params.get(idx).and_then(Value::as_str) {
+        Some(s) => Ok(s.trim_start_matches("0x")),
+        None => invalid_params(),
+    }
+}
+
+fn hash_param(params: &[Value], idx: usize) -> MockResult<H256> {
+    str_param(params, idx)?
+        .parse::<H256>()
+        .or_else(|_| invalid_params())
+}
+
+fn address_param(params: &[Value], idx: usize) -> MockResult<Address> {
+    str_param(params, idx)?
+        .parse::<Address>()
+        .or_else(|_| invalid_params())
+}
+
+fn height_param(params: &[Value], idx: usize, chain: &Chain) -> MockResult<u64> {
+    match params.get(idx).and_then(Value::as_str) {
+        Some("latest") | Some("pending") => Ok(chain.height()),
+        Some("earliest") => Ok(0),
+        Some(_) => u64::from_str_radix(str_param(params, idx)?, 16).or_else(|_| invalid_params()),
+        None => invalid_params(),
+    }
+}
+
+fn filter_id_param(params: &[Value]) -> MockResult<u64> {
+    u64::from_str_radix(str_param(params, 0)?, 16).or_else(|_| invalid_params())
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn metadata(c: &MockConfig, chain: &Chain) -> Value {
+    let block_interval =
+        c.block_interval.as_secs() * 1000 + u64::from(c.block_interval.subsec_millis());
+    json!({
+        "chainId": 0,
+        "chainIdV1": format!("{:#x}", U256::from(c.chain_id)),
+        "chainName": "mock-chain",
+        "operator": "mock-operator",
+        "website": "https://www.example.com",
+        "genesisTimestamp": chain.blocks[0].timestamp,
+        "validators": [],
+        "blockInterval": block_interval,
+        "tokenName": "Mock",
+        "tokenSymbol": "MOCK",
+        "tokenAvatar": "https://www.example.com",
+        "version": 1,
+        "economicalModel": 0,
+    })
+}
+
+fn send_raw_transaction(params: &[Value], chain: &mut Chain) -> MockResult<Value> {
+    let bytes = str_param(params, 0)?
+        .from_hex::<Vec<u8>>()
+        .or_else(|_| invalid_params())?;
+    let utx = UnverifiedTransaction::try_from(&bytes)
+        .map_err(|_| (-32602, "Invalid transaction".to_owned()))?;
+    let valid_until_block = utx.get_transaction().get_valid_until_block();
+    let height = chain.height();
+    if valid_until_block <= height || valid_until_block > height + VALID_BLOCKS {
+        return Err((-32006, "InvalidUntilBlock".to_owned()));
+    }
+    let hash = utx.crypt_hash();
+    if chain.committed.contains_key(&hash) || chain.pending.contains_key(&hash) {
+        return Err((-32006, "Dup".to_owned()));
+    }
+    let signed = SignedTransaction::create(&utx).map_err(|_| (-32006, "InvalidSig".to_owned()))?;
+    let tx = utx.get_transaction();
+    let to = tx.get_to_v1();
+    let tx = MockTx {
+        from: pubkey_to_address(&PubKey::from_slice(signed.get_signer())),
+        to: if to.is_empty() {
+            None
+        } else {
+            Some(Address::from_slice(to))
+        },
+        value: U256::from(tx.get_value()),
+        data: tx.get_data().to_vec(),
+    };
+    if !chain.is_permitted(&tx.from, SEND_TX) {
+        return Err((-32006, "NoTransactionPermission".to_owned()));
+    }
+    if tx.to.is_none() && !chain.is_permitted(&tx.from, CREATE_CONTRACT) {
+        return Err((-32006, "NoContractPermission".to_owned()));
+    }
+    chain.pool.push(hash);
+    chain.pending.insert(hash, tx);
+    Ok(json!({"hash": hex_hash(&hash), "status": "OK"}))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BlockPart {
+    Full,
+    // Only the encoded header, as the proof of the block.
+    Header,
+}
+
+fn block_json(height: u64, chain: &Chain, part: BlockPart) -> Value {
+    let block = match chain.blocks.get(height as usize) {
+        Some(block) => block,
+        None => return Value::Null,
+    };
+    if part == BlockPart::Header {
+        return json!(hex_hash(&block.hash));
+    }
+    let prev_hash = if height == 0 {
+        H256::zero()
+    } else {
+        chain.blocks[height as usize - 1].hash
+    };
+    let transactions = block.tx_hashes.iter().map(hex_hash).collect::<Vec<_>>();
+    json!({
+        "version": 1,
+        "hash": hex_hash(&block.hash),
+        "header": {
+            "timestamp": block.timestamp,
+            "prevHash": hex_hash(&prev_hash),
+            "number": hex_u64(height),
+            "stateRoot": hex_hash(&H256::zero()),
+            "transactionsRoot": hex_hash(&H256::zero()),
+            "receiptsRoot": hex_hash(&H256::zero()),
+            // Both names of the used quota, for the old and the new versions.
+            "gasUsed": "0x0",
+            "quotaUsed": "0x0",
+            "proof": null,
+            "proposer": format!("0x{}", "0".repeat(40)),
+        },
+        "body": {"transactions": transactions},
+    })
+}
+
+fn transaction_json(hash: &H256, chain: &Chain) -> Value {
+    let tx = match chain.committed.get(hash) {
+        Some(tx) => tx,
+        None => return Value::Null,
+    };
+    json!({
+        "hash": hex_hash(hash),
+        "content": "0x",
+        "from": format!("{:#x}", tx.from),
+        "blockNumber": hex_u64(tx.height),
+        "blockHash": hex_hash(&chain.blocks[tx.height as usize].hash),
+        "index": hex_u64(tx.index as u64),
+    })
+}
+
+fn receipt_json(hash: &H256, chain: &Chain) -> Value {
+    let tx = match chain.committed.get(hash) {
+        Some(tx) => tx,
+        None => return Value::Null,
+    };
+    json!({
+        "transactionHash": hex_hash(hash),
+        "transactionIndex": hex_u64(tx.index as u64),
+        "blockHash": hex_hash(&chain.blocks[tx.height as usize].hash),
+        "blockNumber": hex_u64(tx.height),
+        "cumulativeGasUsed": "0x0",
+        "gasUsed": "0x0",
+        "cumulativeQuotaUsed": "0x0",
+        "quotaUsed": "0x0",
+        "contractAddress": tx.contract.map(|address| format!("{:#x}", address)),
+        "logs": [],
+        "root": null,
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "errorMessage": null,
+    })
+}
+
+fn new_filter(filter: Filter, chain: &mut Chain) -> Value {
+    let id = chain.next_filter_id;
+    chain.next_filter_id += 1;
+    chain.filters.insert(id, filter);
+    json!(hex_u64(id))
+}
+
+// The hashes of the blocks since the last poll, or no logs.
+fn filter_changes(id: u64, chain: &mut Chain) -> MockResult<Value> {
+    let height = chain.height();
+    let polled = match chain.filters.get_mut(&id) {
+        Some(Filter::Block(polled)) => polled,
+        Some(Filter::Log) => return Ok(json!([])),
+        None => return Err((-32000, "Filter not found".to_owned())),
+    };
+    let hashes = chain.blocks[*polled as usize + 1..=height as usize]
+        .iter()
+        .map(|block| hex_hash(&block.hash))
+        .collect::<Vec<_>>();
+    *polled = height;
+    Ok(json!(hashes))
+}
+
+fn hex_u64(n: u64) -> String {
+    format!("{:#x}", n)
+}
+
+fn hex_hash(hash: &H256) -> String {
+    format!("{:#x}", hash)
+}
+
+fn random_hash() -> H256 {
+    let mut bytes = [0u8; 32];
+    thread_rng().fill(&mut bytes);
+    H256::from(bytes)
+}
+
+fn now_millis() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    since_epoch.as_secs() * 1000 + u64::from(since_epoch.subsec_millis())
+}
@@ -0,0 +1,302 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use cita_types::H256;
+
+use crate::config::Node;
+use crate::ledger::{Ledger, Transfer};
+use crate::report::{BalanceReport, BlockReport, BlockStat, ConfirmReport, DeliveryReport};
+use crate::transaction::{fetch_block, fetch_height, BlockInfo};
+use crate::transport::{Connector, Web3Client};
+
+const POLL_INTERVAL_MILLIS: u64 = 200;
+// How many blocks to remember the transactions of, for the transactions tracked late.
+const RECENT_BLOCKS: u64 = 20;
+
+#[derive(Debug)]
+struct PendingTx {
+    node_id: usize,
+    sent_at: Instant,
+    valid_until_block: u64,
+    transfer: Option<Transfer>,
+}
+
+/// Watches new blocks during the benchmark.
+///
+/// It collects statistics of blocks, and confirms the transactions which
+/// were sent when tracking is enabled.
+#[derive(Debug)]
+pub struct Observer {
+    node: Vec<Node>,
+    connector: Connector,
+    // Wait for the sent transactions to be committed, only when tracking.
+    confirm_timeout: Option<Duration>,
+    pending: Mutex<HashMap<H256, PendingTx>>,
+    // The untracked transactions in the recent blocks, with the heights and when they were
+    // found. A transaction may be committed before its response comes and it is tracked.
+    recent: Mutex<HashMap<H256, (u64, Instant)>>,
+    // When all transactions were sent.
+    finished: RwLock<Option<Instant>>,
+    // The latest height which is polled, the transactions are valid until a height after it.
+    latest_height: RwLock<Option<u64>>,
+    report: Mutex<Vec<ConfirmReport>>,
+    block_report: Mutex<BlockReport>,
+    // Timestamps of the observed blocks, to match the delivered blocks.
+    block_timestamps: Mutex<HashMap<H256, u64>>,
+    // The blocks delivered by subscriptions, and when they were received in milliseconds.
+    deliveries: Mutex<Vec<(H256, u64)>>,
+    // Only when the transfers are verified.
+    ledger: Option<Mutex<Ledger>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Observer {
+    pub fn start(
+        connector: &Connector,
+        node: &[Node],
+        confirm_timeout: Option<Duration>,
+        ledger: Option<Ledger>,
+    ) -> Arc<Self> {
+        let report = node.iter().map(|_| ConfirmReport::new()).collect();
+        // Fetch it before the soldiers start, they build the transactions by it.
+        let latest_height = {
+            let (_eloop, web3) = connector.connect(&node[0]);
+            fetch_height(&web3)
+                .map_err(|err| log::warn!("Observer failed to fetch height: {}", err))
+                .ok()
+        };
+        let observer = Arc::new(Self {
+            node: node.to_vec(),
+            connector: connector.clone(),
+            confirm_timeout,
+            pending: Mutex::new(HashMap::new()),
+            recent: Mutex::new(HashMap::new()),
+            finished: RwLock::new(None),
+            latest_height: RwLock::new(latest_height),
+            report: Mutex::new(report),
+            block_report: Mutex::new(BlockReport::new()),
+            block_timestamps: Mutex::new(HashMap::new()),
+            deliveries: Mutex::new(Vec::new()),
+            ledger: ledger.map(Mutex::new),
+            handle: Mutex::new(None),
+        });
+        let observer_clone = observer.clone();
+        let handle = thread::spawn(move || {
+            let (_eloop, web3) = observer_clone.connector.connect(&observer_clone.node[0]);
+            observer_clone.observe(&web3);
+        });
+        *observer.handle.lock().unwrap() = Some(handle);
+        observer
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        self.confirm_timeout.is_some()
+    }
+
+    /// The latest height of the chain, None if it has never been fetched.
+    pub fn latest_height(&self) -> Option<u64> {
+        *self.latest_height.read().unwrap()
+    }
+
+    pub fn track(
+        &self,
+        node: &Node,
+        hash: H256,
+        sent_at: Instant,
+        valid_until_block: u64,
+        transfer: Option<Transfer>,
+    ) {
+        let node_id = self
+            .node
+            .iter()
+            .position(|n| n == node)
+            .expect("track a transaction for an unknown node");
+        let tx = PendingTx {
+            node_id,
+            sent_at,
+            valid_until_block,
+            transfer,
+        };
+        let mut pending = self.pending.lock().unwrap();
+        let found = self.recent.lock().unwrap().remove(&hash);
+        if let Some((_, found_at)) = found {
+            self.commit(hash, tx, found_at);
+        } else {
+            pending.insert(hash, tx);
+        }
+    }
+
+    /// Tracks a transaction which is sent by a custom workload, it waits until the timeout
+    /// since the block which it is valid until is unknown.
+    pub fn track_sent(&self, node: &Node, hash: H256, sent_at: Instant) {
+        self.track(node, hash, sent_at, u64::max_value(), None);
+    }
+
+    pub fn deliver(&self, hash: H256, received: u64) {
+        self.deliveries.lock().unwrap().push((hash, received));
+    }
+
+    /// Waits until all transactions are confirmed or the timeout is reached.
+    pub fn finish(&self) -> (Option<Vec<ConfirmReport>>, BlockReport) {
+        {
+            *self.finished.write().unwrap() = Some(Instant::now());
+        }
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            handle.join().unwrap();
+        }
+        let confirm_report = if self.is_tracking() {
+            let mut report = self.report.lock().unwrap();
+            Some(report.drain(..).collect())
+        } else {
+            None
+        };
+        let block_report = {
+            let mut block_report = self.block_report.lock().unwrap();
+            ::std::mem::replace(&mut *block_report, BlockReport::new())
+        };
+        (confirm_report, block_report)
+    }
+
+    /// Measures the latency from the timestamp of each block to its delivery, call it after
+    /// finished.
+    pub fn delivery_report(&self) -> Option<DeliveryReport> {
+        let deliveries = self.deliveries.lock().unwrap();
+        if deliveries.is_empty() {
+            return None;
+        }
+        let block_timestamps = self.block_timestamps.lock().unwrap();
+        let mut report = DeliveryReport::new();
+        for (hash, received) in deliveries.iter() {
+            if let Some(timestamp) = block_timestamps.get(hash) {
+                report.add_delivered(received.saturating_sub(*timestamp));
+            } else {
+                report.add_unmatched();
+            }
+        }
+        Some(report)
+    }
+
+    /// Checks the balances by the committed transfers, call it after finished.
+    pub fn verify_balances(&self) -> Option<BalanceReport> {
+        let ledger = self.ledger.as_ref()?;
+        let (_eloop, web3) = self.connector.connect(&self.node[0]);
+        let report = ledger.lock().unwrap().verify(&web3);
+        Some(report)
+    }
+
+    fn observe(&self, web3: &Web3Client) {
+        // Starts from the first height which is fetched.
+        let mut height = None;
+        let wait_millis = Duration::from_millis(POLL_INTERVAL_MILLIS);
+        loop {
+            match fetch_height(web3) {
+                Ok(latest) => {
+                    *self.latest_height.write().unwrap() = Some(latest);
+                    let height = height.get_or_insert_with(|| {
+                        log::debug!("Observer starts from block#{}.", latest);
+                        latest
+                    });
+                    while *height < latest {
+                        match fetch_block(web3, *height + 1) {
+                            Ok(block) => {
+                                *height += 1;
+                                self.confirm(&block);
+                            }
+                            Err(err) => {
+                                log::warn!("Observer failed to fetch block: {}", err);
+                                break;
+                            }
+                        }
+                    }
+                    self.expire(*height);
+                }
+                Err(err) => log::warn!("Observer failed to fetch height: {}", err),
+            }
+            let finished = { *self.finished.read().unwrap() };
+            if let Some(finished) = finished {
+                let timeout = self.confirm_timeout.unwrap_or_default();
+                if self.pending.lock().unwrap().is_empty() || finished.elapsed() >= timeout {
+                    break;
+                }
+            }
+            thread::sleep(wait_millis);
+        }
+        let mut pending = self.pending.lock().unwrap();
+        let mut report = self.report.lock().unwrap();
+        for (_, tx) in pending.drain() {
+            report[tx.node_id].add_unconfirmed();
+        }
+        if let Some(height) = height {
+            log::debug!("Observer stops at block#{}.", height);
+        }
+    }
+
+    fn confirm(&self, block: &BlockInfo) {
+        let now = Instant::now();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            let mut recent = self.recent.lock().unwrap();
+            for hash in block.tx_hashes.iter() {
+                if let Some(tx) = pending.remove(hash) {
+                    self.commit(*hash, tx, now);
+                } else if self.is_tracking() {
+                    recent.insert(*hash, (block.height, now));
+                }
+            }
+        }
+        self.block_timestamps
+            .lock()
+            .unwrap()
+            .insert(block.hash, block.timestamp);
+        self.block_report.lock().unwrap().add(BlockStat {
+            height: block.height,
+            timestamp: block.timestamp,
+            tx_cnt: block.tx_hashes.len(),
+        });
+        log::trace!(
+            "Block#{} has {} transactions.",
+            block.height,
+            block.tx_hashes.len()
+        );
+    }
+
+    fn commit(&self, hash: H256, tx: PendingTx, committed_at: Instant) {
+        let latency = if committed_at > tx.sent_at {
+            committed_at - tx.sent_at
+        } else {
+            Duration::new(0, 0)
+        };
+        self.report.lock().unwrap()[tx.node_id].add_committed(latency);
+        if let (Some(transfer), Some(ledger)) = (tx.transfer, self.ledger.as_ref()) {
+            ledger.lock().unwrap().commit(hash, &transfer);
+        }
+    }
+
+    fn expire(&self, height: u64) {
+        self.recent
+            .lock()
+            .unwrap()
+            .retain(|_, (found, _)| *found + RECENT_BLOCKS > height);
+        let mut pending = self.pending.lock().unwrap();
+        let mut report = self.report.lock().unwrap();
+        let expired = pending
+            .iter()
+            .filter(|(_, tx)| tx.valid_until_block < height)
+            .map(|(hash, _)| *hash)
+            .collect::<Vec<_>>();
+        for hash in expired.iter() {
+            let tx = pending.remove(hash).unwrap();
+            report[tx.node_id].add_missing();
+        }
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cita_types::{Address, H256, U256};
+use cita_web3::{
+    api::Cita,
+    types::{self, request::JsonRpcRequest},
+    web3::{futures::Future, Transport},
+};
+use ethabi::{ParamType, Token};
+use serde_json::Value;
+
+use crate::abi::encode_call;
+use crate::account::PrivateKey;
+use crate::transaction::{fetch_height, sign_transaction, TxParams};
+use crate::transport::{AnyTransport, Web3Client};
+
+// The system contract which manages the permissions of accounts.
+pub const PERMISSION_MANAGEMENT: &str = "ffffffffffffffffffffffffffffffffff020004";
+// The built-in permissions to send transactions and to create contracts.
+pub const SEND_TX: &str = "ffffffffffffffffffffffffffffffffff021000";
+pub const CREATE_CONTRACT: &str = "ffffffffffffffffffffffffffffffffff021001";
+const GRANT_FUNCTION: &str = "setAuthorizations(address,address[])";
+// Enough for each grant.
+const GRANT_QUOTA: u64 = 1_000_000;
+// How long to wait for all grants to be committed.
+const GRANT_TIMEOUT_SECS: u64 = 60;
+// How often the receipts of the grants are polled.
+const POLL_INTERVAL_MILLIS: u64 = 200;
+
+/// Grants the accounts the permissions to send transactions and to create contracts on a
+/// permissioned chain, by the transactions of an admin. Returns after all of them are committed.
+pub fn grant_permissions(
+    web3: &Web3Client,
+    admin: &PrivateKey,
+    chain_id: U256,
+    accounts: &[Address],
+) -> Result<(), String> {
+    let height =
+        fetch_height(web3).map_err(|err| format!("failed to fetch the height: {}", err))?;
+    let mut hashes = Vec::new();
+    for account in accounts {
+        let args = [
+            format!("{:x}", account),
+            format!("[{},{}]", SEND_TX, CREATE_CONTRACT),
+        ];
+        let tx_params = TxParams {
+            to: Some(PERMISSION_MANAGEMENT.parse().unwrap()),
+            data: encode_call(GRANT_FUNCTION, &args)?,
+            quota: GRANT_QUOTA,
+            value: U256::zero(),
+            receivers: Vec::new(),
+        };
+        let (tx, _) = sign_transaction(admin, chain_id, height + 100, &tx_params);
+        let param = types::request::SendRawTransactionParams::new(tx.bytes.into());
+        let resp = web3
+            .api::<Cita<AnyTransport>>()
+            .call(param)
+            .wait()
+            .map_err(|err| format!("failed to grant {:?}: {}", account, err))?;
+        if resp.status != "OK" {
+            return Err(format!("failed to grant {:?}: {}", account, resp.status));
+        }
+        hashes.push((*account, resp.hash));
+    }
+    let deadline = Instant::now() + Duration::from_secs(GRANT_TIMEOUT_SECS);
+    while !hashes.is_empty() {
+        if Instant::now() > deadline {
+            let accounts = hashes
+                .iter()
+                .map(|(account, _)| account)
+                .collect::<Vec<_>>();
+            return Err(format!("the grants to {:?} are not committed", accounts));
+        }
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MILLIS));
+        let mut pending = Vec::new();
+        for (account, hash) in hashes {
+            match fetch_receipt(web3, hash)? {
+                Some(receipt) => match receipt["errorMessage"].as_str() {
+                    Some(msg) => return Err(format!("failed to grant {:?}: {}", account, msg)),
+                    None => log::info!("Granted the permissions to {:?}.", account),
+                },
+                None => pending.push((account, hash)),
+            }
+        }
+        hashes = pending;
+    }
+    Ok(())
+}
+
+// None if the transaction is not committed yet.
+fn fetch_receipt(web3: &Web3Client, hash: H256) -> Result<Option<Value>, String> {
+    let param = types::request::GetTransactionReceiptParams::new(hash.into());
+    let receipt = web3
+        .transport()
+        .execute(param.method_name(), param.value_vec())
+        .wait()
+        .map_err(|err| format!("failed to fetch the receipt of {:?}: {}", hash, err))?;
+    Ok(if receipt.is_null() {
+        None
+    } else {
+        Some(receipt)
+    })
+}
+
+/// Decodes the account and the permissions of a grant, None if the call is not a grant.
+pub fn decode_grant(data: &[u8]) -> Option<(Address, Vec<Address>)> {
+    let args = ["0".repeat(40), "[]".to_owned()];
+    let selector = encode_call(GRANT_FUNCTION, &args).ok()?;
+    if data.len() < 4 || data[..4] != selector[..4] {
+        return None;
+    }
+    let kinds = [
+        ParamType::Address,
+        ParamType::Array(Box::new(ParamType::Address)),
+    ];
+    let mut tokens = ethabi::decode(&kinds, &data[4..]).ok()?.into_iter();
+    let to_address = |token: Token| match token {
+        Token::Address(address) => Some(Address::from_slice(&address.0)),
+        _ => None,
+    };
+    let account = to_address(tokens.next()?)?;
+    let permissions = match tokens.next()? {
+        Token::Array(permissions) => permissions
+            .into_iter()
+            .map(to_address)
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    Some((account, permissions))
+}
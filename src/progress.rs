@@ -0,0 +1,103 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::report::{duration_to_millis, SimpleReport};
+
+const TICK_MILLIS: u64 = 100;
+// How often soldiers push their incremental reports.
+pub const PUSH_INTERVAL_MILLIS: u64 = 500;
+
+/// Prints the throughput and the latency of each interval during the run.
+#[derive(Debug)]
+pub struct Progress {
+    sender: Mutex<Option<Sender<SimpleReport>>>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Progress {
+    pub fn start(interval: Duration, terminate: Arc<RwLock<bool>>) -> Arc<Self> {
+        let (tx, rx) = channel();
+        let handle = thread::spawn(move || {
+            report_progress(&rx, interval, &terminate);
+        });
+        Arc::new(Self {
+            sender: Mutex::new(Some(tx)),
+            handle: Mutex::new(Some(handle)),
+        })
+    }
+
+    pub fn sender(&self) -> Sender<SimpleReport> {
+        self.sender
+            .lock()
+            .unwrap()
+            .as_ref()
+            .expect("progress has finished")
+            .clone()
+    }
+
+    /// Waits until the last progress is printed.
+    pub fn finish(&self) {
+        {
+            self.sender.lock().unwrap().take();
+        }
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            handle.join().unwrap();
+        }
+    }
+}
+
+fn report_progress(rx: &Receiver<SimpleReport>, interval: Duration, terminate: &RwLock<bool>) {
+    let start = Instant::now();
+    let mut last = start;
+    let mut current = SimpleReport::new();
+    let mut terminated = false;
+    loop {
+        match rx.recv_timeout(Duration::from_millis(TICK_MILLIS)) {
+            Ok(rpt) => current.merge(&rpt),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        let stopping = !terminated && *terminate.read().unwrap();
+        if stopping || last.elapsed() >= interval {
+            print_progress(start, last, &current);
+            last = Instant::now();
+            current = SimpleReport::new();
+            terminated = terminated || stopping;
+        }
+    }
+    if current.get_success_cnt() + current.get_failure_cnt() != 0 {
+        print_progress(start, last, &current);
+    }
+}
+
+fn print_progress(start: Instant, last: Instant, rpt: &SimpleReport) {
+    let elapsed = duration_to_millis(start.elapsed());
+    let period = duration_to_millis(last.elapsed());
+    let rate = if period > 0.0 {
+        rpt.get_success_cnt() as f64 / (period / 1e3)
+    } else {
+        0.0
+    };
+    eprintln!(
+        "[{:10.3} s] {:10.3} req/s  Succ {:8}  Fail {:6}  P50 {:9.3} ms  P90 {:9.3} ms  \
+         P99 {:9.3} ms  Max {:9.3} ms",
+        elapsed / 1e3,
+        rate,
+        rpt.get_success_cnt(),
+        rpt.get_failure_cnt(),
+        duration_to_millis(rpt.get_success_tm_quantile(0.5)),
+        duration_to_millis(rpt.get_success_tm_quantile(0.9)),
+        duration_to_millis(rpt.get_success_tm_quantile(0.99)),
+        duration_to_millis(rpt.get_success_tm_max()),
+    );
+}
@@ -0,0 +1,192 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cita_types::U256;
+use rand::distributions::WeightedIndex;
+use rand::{thread_rng, Rng};
+use rustc_hex::{FromHex, ToHex};
+
+use crate::abi::HexAddress;
+use crate::config::AppConfig;
+use crate::ledger::Transfer;
+use crate::transaction::{
+    fetch_chain_id, fetch_height, is_tx_category, sign_transaction, SignedTx, TxParams,
+    JSONRPC_METHODS,
+};
+
+// The furthest block which CITA accepts for `valid_until_block`.
+const VALID_BLOCKS: u64 = 100;
+
+/// A signed transaction in the file, with what it is generated for.
+#[derive(Debug, Clone)]
+pub struct ReplayTx {
+    pub category: &'static str,
+    pub tx: SignedTx,
+    // Only for transfers, to verify the balances after they are replayed.
+    pub transfer: Option<Transfer>,
+}
+
+/// Transactions to replay, each one is sent only once by any soldier.
+#[derive(Debug)]
+pub struct Replay {
+    txs: Vec<ReplayTx>,
+    next: AtomicUsize,
+}
+
+impl Replay {
+    pub fn new(txs: Vec<ReplayTx>) -> Self {
+        Self {
+            txs,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The categories of the transactions, in the order they first appear.
+    pub fn categories(&self) -> Vec<&'static str> {
+        let mut categories = Vec::new();
+        for tx in self.txs.iter() {
+            if !categories.contains(&tx.category) {
+                categories.push(tx.category);
+            }
+        }
+        categories
+    }
+
+    /// Takes the next transaction, or none if all are taken.
+    pub fn next(&self) -> Option<&ReplayTx> {
+        let idx = self.next.fetch_add(1, Ordering::SeqCst);
+        self.txs.get(idx)
+    }
+}
+
+/// Loads signed transactions from a file.
+///
+/// Each line has the `valid_until_block`, the category and the transaction in hex with the
+/// 0x-prefix, separated by spaces. A transfer is followed by its sender, receiver and value.
+/// Empty lines and lines start with `#` are ignored.
+pub fn load_signed_txs(path: &str) -> Result<Vec<ReplayTx>, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read the transactions file {}: {}", path, err))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|(idx, line)| {
+            parse_signed_tx(line.trim()).map_err(|err| format!("{}:{}: {}", path, idx + 1, err))
+        })
+        .collect()
+}
+
+fn parse_signed_tx(line: &str) -> Result<ReplayTx, String> {
+    let v = line.split_whitespace().collect::<Vec<_>>();
+    if (v.len() != 3 && v.len() != 6) || !v[2].starts_with("0x") {
+        return Err("the signed transaction is malformed".to_owned());
+    }
+    let valid_until_block = v[0]
+        .parse::<u64>()
+        .map_err(|err| format!("the valid until block is malformed: {}", err))?;
+    let category = JSONRPC_METHODS
+        .iter()
+        .find(|method| **method == v[1] && is_tx_category(method))
+        .ok_or_else(|| format!("{} is not a transaction", v[1]))?;
+    let bytes = v[2][2..]
+        .from_hex()
+        .map_err(|err| format!("the transaction is malformed: {}", err))?;
+    let transfer = if v.len() == 6 {
+        let value =
+            U256::from_dec_str(v[5]).map_err(|err| format!("the value is malformed: {:?}", err))?;
+        Some(Transfer {
+            from: v[3].parse::<HexAddress>()?.0,
+            to: v[4].parse::<HexAddress>()?.0,
+            value,
+        })
+    } else {
+        None
+    };
+    if (*category == "transfer") != transfer.is_some() {
+        return Err("only a transfer has its sender, receiver and value".to_owned());
+    }
+    Ok(ReplayTx {
+        category,
+        tx: SignedTx {
+            valid_until_block,
+            bytes,
+        },
+        transfer,
+    })
+}
+
+/// Signs transactions and writes them into the output file.
+///
+/// They are valid until 100 blocks after the current height, so replay them soon.
+pub fn generate(c: &AppConfig) -> Result<(), String> {
+    let path = c
+        .output_file
+        .as_ref()
+        .ok_or_else(|| "\"output-file\" is required to generate".to_owned())?;
+    if c.amount == 0 {
+        return Err("\"amount\" is required to generate".to_owned());
+    }
+    if let Some(item) = c.mix.iter().find(|item| !is_tx_category(&item.category)) {
+        return Err(format!("{} is not a transaction", item.category));
+    }
+    let (_eloop, web3) = c.connector.connect(&c.node[0]);
+    let chain_id =
+        fetch_chain_id(&web3).map_err(|err| format!("failed to fetch the chain id: {}", err))?;
+    let valid_until_block = fetch_height(&web3)
+        .map_err(|err| format!("failed to fetch the height: {}", err))?
+        + VALID_BLOCKS;
+    let mix = c
+        .mix
+        .iter()
+        .map(|item| (item.category.as_str(), TxParams::new(&item.category, c)))
+        .collect::<Vec<_>>();
+    let weights = WeightedIndex::new(c.mix.iter().map(|item| item.weight)).unwrap();
+    let mut rng = thread_rng();
+    let write_err = |err: io::Error| format!("failed to write {}: {}", path, err);
+    let file = File::create(path).map_err(write_err)?;
+    let mut out = BufWriter::new(file);
+    writeln!(out, "# {} transactions for [{}]", c.amount, c.title()).map_err(write_err)?;
+    for idx in 0..c.amount {
+        let key = &c.key[idx % c.key.len()];
+        let (category, tx_params) = &mix[rng.sample(&weights)];
+        let (tx, transfer) = sign_transaction(key, chain_id, valid_until_block, tx_params);
+        write!(
+            out,
+            "{} {} 0x{}",
+            tx.valid_until_block,
+            category,
+            tx.bytes.to_hex::<String>()
+        )
+        .map_err(write_err)?;
+        if let Some(transfer) = transfer {
+            write!(
+                out,
+                " {:#x} {:#x} {}",
+                transfer.from, transfer.to, transfer.value
+            )
+            .map_err(write_err)?;
+        }
+        writeln!(out).map_err(write_err)?;
+    }
+    out.flush().map_err(write_err)?;
+    log::info!(
+        "Generated {} transactions into {}, valid until block#{}.",
+        c.amount,
+        path,
+        valid_until_block
+    );
+    Ok(())
+}
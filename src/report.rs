@@ -6,20 +6,70 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::io::{stdout, Write};
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, stdout, Write};
+use std::iter;
 use std::time::Duration;
 
+use hdrhistogram::Histogram;
+use serde_derive::Serialize;
 use tabwriter::TabWriter;
 
+use cita_types::{Address, U256};
+
 use crate::config::Node;
+use crate::scenario::Phase;
+
+// Latencies are recorded in microseconds, the histograms cover one second at first and grow
+// when a longer one is recorded.
+const HIST_LOWEST: u64 = 1;
+const HIST_HIGHEST: u64 = 1_000_000;
+const HIST_SIGFIG: u8 = 3;
+
+pub const OUTPUT_FORMATS: &[&str] = &["table", "json", "csv"];
+
+const PERCENTILES: &[(&str, f64)] = &[("P50", 0.5), ("P90", 0.9), ("P99", 0.99), ("P99.9", 0.999)];
+
+// How many distinct error messages are shown for each node.
+const TOP_ERRORS: usize = 5;
+// Messages may embed request-specific data, keep them short.
+const ERROR_MESSAGE_MAX_LEN: usize = 120;
+
+pub(crate) fn duration_to_millis(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1e3 + f64::from(d.subsec_nanos()) * 1e-6
+}
+
+fn duration_to_micros(d: Duration) -> u64 {
+    d.as_secs() * 1_000_000 + u64::from(d.subsec_micros())
+}
+
+fn new_histogram() -> Histogram<u64> {
+    let mut hist = Histogram::new_with_bounds(HIST_LOWEST, HIST_HIGHEST, HIST_SIGFIG).unwrap();
+    hist.auto(true);
+    hist
+}
 
 #[derive(Debug)]
 pub struct GeneralReport {
     pub(crate) title: String,
     pub(crate) ready_tm: Duration,
     pub(crate) cost_tm: Duration,
+    // Samples during the warmup are not recorded.
+    pub(crate) warmup_tm: Duration,
     pub(crate) node: Vec<Node>,
+    // Only when a scenario is followed, the samples are split by them.
+    pub(crate) phases: Vec<Phase>,
     pub(crate) captain_report: Vec<CaptainReport>,
+    // Only available when the transactions are tracked, in the same order as nodes.
+    pub(crate) confirm_report: Option<Vec<ConfirmReport>>,
+    pub(crate) block_report: Option<BlockReport>,
+    // Only available when the transfers are verified.
+    pub(crate) balance_report: Option<BalanceReport>,
+    // Only available when blocks are delivered by subscriptions.
+    pub(crate) delivery_report: Option<DeliveryReport>,
 }
 
 impl GeneralReport {
@@ -28,50 +78,785 @@ impl GeneralReport {
             title,
             ready_tm: Duration::new(0, 0),
             cost_tm: Duration::new(0, 0),
+            warmup_tm: Duration::new(0, 0),
             node: Vec::<Node>::with_capacity(sz),
+            phases: Vec::new(),
             captain_report: Vec::with_capacity(sz),
+            confirm_report: None,
+            block_report: None,
+            balance_report: None,
+            delivery_report: None,
+        }
+    }
+
+    pub fn output(&self, format: &str, file: Option<&str>) -> io::Result<()> {
+        if let Some(path) = file {
+            File::create(path).and_then(|mut f| self.write(format, &mut f))
+        } else {
+            self.write(format, &mut stdout())
+        }
+    }
+
+    /// The cost time without the warmup.
+    pub fn get_measured_tm(&self) -> Duration {
+        if self.cost_tm > self.warmup_tm {
+            self.cost_tm - self.warmup_tm
+        } else {
+            self.cost_tm
+        }
+    }
+
+    /// The measured time in the phase, which is shorter than it if the mission stops early.
+    fn get_phase_tm(&self, idx: usize) -> Duration {
+        let begin = self.phases[..idx]
+            .iter()
+            .fold(Duration::new(0, 0), |sum, phase| sum + phase.duration);
+        let end = cmp::min(begin + self.phases[idx].duration, self.cost_tm);
+        let begin = cmp::max(begin, self.warmup_tm);
+        if end > begin {
+            end - begin
+        } else {
+            Duration::new(0, 0)
+        }
+    }
+
+    fn write<W: Write>(&self, format: &str, out: &mut W) -> io::Result<()> {
+        match format {
+            "table" => self.write_table(out),
+            "json" => self.write_json(out),
+            "csv" => self.write_csv(out),
+            _ => unreachable!(),
         }
     }
 
-    pub fn print(&self) {
-        println!("----    ----    ----    ----    ----    ----    ----    ----    ----    ----");
-        println!("{:-24}Benchmark [{}]\n", "", self.title);
-        let mut total_success_cnt = 0;
-        let mut out = TabWriter::new(stdout());
+    fn write_table<W: Write>(&self, out: &mut W) -> io::Result<()> {
         writeln!(
             out,
-            "Node\tAmount\tThread\tSuccess\tFailure\tMissing\tSuccCostAvg (ms)"
+            "----    ----    ----    ----    ----    ----    ----    ----    ----    ----"
+        )?;
+        writeln!(out, "{:-24}Benchmark [{}]\n", "", self.title)?;
+        let mut total = SimpleReport::new();
+        {
+            let mut tw = TabWriter::new(&mut *out);
+            write!(
+                tw,
+                "Node\tAmount\tThread\tSuccess\tFailure\tMissing\tSuccCostAvg (ms)"
+            )?;
+            for (name, _) in PERCENTILES.iter() {
+                write!(tw, "\t{} (ms)", name)?;
+            }
+            writeln!(tw, "\tMax (ms)")?;
+            for crpt in self.captain_report.iter() {
+                let rpt = crpt.analyse();
+                let node = &self.node[crpt.captain_id];
+                write!(tw, "{}:{}\t", node.host, node.port)?;
+                write!(
+                    tw,
+                    "{}\t{}\t{}\t{}\t",
+                    rpt.success_cnt + rpt.failure_cnt,
+                    crpt.soldier_report.len(),
+                    rpt.success_cnt,
+                    rpt.failure_cnt,
+                )?;
+                if let Some(ref confirm_report) = self.confirm_report {
+                    write!(tw, "{}\t", confirm_report[crpt.captain_id].missing_cnt)?;
+                } else {
+                    write!(tw, "-\t")?;
+                }
+                write!(tw, "{:.6}", duration_to_millis(rpt.get_success_tm()))?;
+                for (_, quantile) in PERCENTILES.iter() {
+                    let tm = rpt.get_success_tm_quantile(*quantile);
+                    write!(tw, "\t{:.3}", duration_to_millis(tm))?;
+                }
+                writeln!(tw, "\t{:.3}", duration_to_millis(rpt.get_success_tm_max()))?;
+                total.merge(&rpt);
+            }
+            writeln!(tw)?;
+            tw.flush()?;
+        }
+        self.write_categories(out, &total)?;
+        self.write_phases(out, &total)?;
+        self.write_failures(out)?;
+        self.write_confirmation(out)?;
+        self.write_blocks(out)?;
+        self.write_balances(out)?;
+        let total_cost_tm = duration_to_millis(self.cost_tm);
+        let tps = total.success_cnt as f64 / (duration_to_millis(self.get_measured_tm()) / 1e3);
+        writeln!(out, "{:-24}Total Cost : {:12.3} ms", "", total_cost_tm)?;
+        if self.warmup_tm != Duration::new(0, 0) {
+            let warmup_tm = duration_to_millis(self.warmup_tm);
+            writeln!(out, "{:-24}Warmup     : {:12.3} ms", "", warmup_tm)?;
+        }
+        writeln!(out, "{:-24}Total Succ : {:12} tx", "", total.success_cnt)?;
+        writeln!(out, "{:-24}    TPS    : {:12.3} tx/s", "", tps)?;
+        for (name, quantile) in PERCENTILES.iter() {
+            let tm = total.get_success_tm_quantile(*quantile);
+            writeln!(
+                out,
+                "{:-24}{:-11}: {:12.3} ms",
+                "",
+                name,
+                duration_to_millis(tm)
+            )?;
+        }
+        let tm = total.get_success_tm_max();
+        writeln!(
+            out,
+            "{:-24}{:-11}: {:12.3} ms",
+            "",
+            "Max",
+            duration_to_millis(tm)
+        )?;
+        if total.batch_cnt != 0 {
+            writeln!(out, "{:-24}Batches    : {:12}", "", total.batch_cnt)?;
+            for (name, quantile) in PERCENTILES.iter() {
+                let tm = total.get_batch_tm_quantile(*quantile);
+                writeln!(
+                    out,
+                    "{:-24}Batch{:-6}: {:12.3} ms",
+                    "",
+                    name,
+                    duration_to_millis(tm)
+                )?;
+            }
+        }
+        if let Some(ref block_report) = self.block_report {
+            let blk = block_report.analyse();
+            writeln!(out, "{:-24}Blocks     : {:12}", "", blk.count)?;
+            writeln!(
+                out,
+                "{:-24}Txs/Block  : {:12.3} tx",
+                "", blk.tx_per_block_avg
+            )?;
+            writeln!(
+                out,
+                "{:-24}Interval   : {:12.3} ms",
+                "", blk.interval_avg_ms
+            )?;
+            writeln!(out, "{:-24}Chain TPS  : {:12.3} tx/s", "", blk.tps)?;
+        }
+        if let Some(ref delivery_report) = self.delivery_report {
+            let dlv = delivery_report.analyse();
+            writeln!(out, "{:-24}Delivered  : {:12}", "", dlv.delivered)?;
+            writeln!(out, "{:-24}Unmatched  : {:12}", "", dlv.unmatched)?;
+            for (name, quantile) in PERCENTILES.iter() {
+                let tm = delivery_report.get_delivery_tm_quantile(*quantile);
+                writeln!(
+                    out,
+                    "{:-24}Deliver{:-4}: {:12.3} ms",
+                    "",
+                    name,
+                    duration_to_millis(tm)
+                )?;
+            }
+        }
+        if let Some(ref balance_report) = self.balance_report {
+            let bal = balance_report.analyse();
+            let checked = format!("{}/{}", bal.matched, bal.accounts.len());
+            writeln!(out, "{:-24}Balance OK : {:>12}", "", checked)?;
+        }
+        writeln!(
+            out,
+            "----    ----    ----    ----    ----    ----    ----    ----    ----    ----"
         )
-        .unwrap();
+    }
+
+    fn write_categories<W: Write>(&self, out: &mut W, total: &SimpleReport) -> io::Result<()> {
+        if total.category.is_empty() {
+            return Ok(());
+        }
+        let mut tw = TabWriter::new(&mut *out);
+        write!(
+            tw,
+            "Node\tCategory\tAmount\tSuccess\tFailure\tSuccCostAvg (ms)"
+        )?;
+        for (name, _) in PERCENTILES.iter() {
+            write!(tw, "\t{} (ms)", name)?;
+        }
+        writeln!(tw, "\tMax (ms)")?;
+        let analysed = self
+            .captain_report
+            .iter()
+            .map(|crpt| (self.node[crpt.captain_id].to_string(), crpt.analyse()))
+            .collect::<Vec<_>>();
+        let rows = analysed
+            .iter()
+            .map(|(node, rpt)| (node.as_str(), rpt))
+            .chain(iter::once(("Total", total)));
+        for (node, rpt) in rows {
+            for (category, crpt) in rpt.category.iter() {
+                write!(
+                    tw,
+                    "{}\t{}\t{}\t{}\t{}\t{:.6}",
+                    node,
+                    category,
+                    crpt.success_cnt + crpt.failure_cnt,
+                    crpt.success_cnt,
+                    crpt.failure_cnt,
+                    duration_to_millis(crpt.get_success_tm())
+                )?;
+                for (_, quantile) in PERCENTILES.iter() {
+                    let tm = crpt.get_success_tm_quantile(*quantile);
+                    write!(tw, "\t{:.3}", duration_to_millis(tm))?;
+                }
+                writeln!(tw, "\t{:.3}", duration_to_millis(crpt.get_success_tm_max()))?;
+            }
+        }
+        writeln!(tw)?;
+        tw.flush()
+    }
+
+    fn write_phases<W: Write>(&self, out: &mut W, total: &SimpleReport) -> io::Result<()> {
+        if self.phases.is_empty() {
+            return Ok(());
+        }
+        let mut tw = TabWriter::new(&mut *out);
+        write!(
+            tw,
+            "Phase\tDuration\tRate\tThread\tAmount\tSuccess\tFailure\tTPS\tSuccCostAvg (ms)"
+        )?;
+        for (name, _) in PERCENTILES.iter() {
+            write!(tw, "\t{} (ms)", name)?;
+        }
+        writeln!(tw, "\tMax (ms)")?;
+        let empty = SimpleReport::new();
+        for (idx, phase) in self.phases.iter().enumerate() {
+            let prpt = total.phase.get(idx).unwrap_or(&empty);
+            let rate = if !phase.is_rated() {
+                "-".to_owned()
+            } else if phase.rate == phase.rate_to {
+                phase.rate.to_string()
+            } else {
+                format!("{}->{}", phase.rate, phase.rate_to)
+            };
+            let thread = phase
+                .thread
+                .map(|thread| thread.to_string())
+                .unwrap_or_else(|| "-".to_owned());
+            let phase_tm = self.get_phase_tm(idx);
+            write!(
+                tw,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.3}\t{:.6}",
+                phase.name,
+                humantime::format_duration(phase.duration),
+                rate,
+                thread,
+                prpt.success_cnt + prpt.failure_cnt,
+                prpt.success_cnt,
+                prpt.failure_cnt,
+                prpt.success_cnt as f64 / (duration_to_millis(phase_tm) / 1e3),
+                duration_to_millis(prpt.get_success_tm())
+            )?;
+            for (_, quantile) in PERCENTILES.iter() {
+                let tm = prpt.get_success_tm_quantile(*quantile);
+                write!(tw, "\t{:.3}", duration_to_millis(tm))?;
+            }
+            writeln!(tw, "\t{:.3}", duration_to_millis(prpt.get_success_tm_max()))?;
+        }
+        writeln!(tw)?;
+        tw.flush()
+    }
+
+    fn write_failures<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let analysed = self
+            .captain_report
+            .iter()
+            .map(|crpt| (&self.node[crpt.captain_id], crpt.analyse()))
+            .filter(|(_, rpt)| rpt.failure_cnt != 0)
+            .collect::<Vec<_>>();
+        if analysed.is_empty() {
+            return Ok(());
+        }
+        let mut tw = TabWriter::new(&mut *out);
+        writeln!(tw, "Node\tFailure\tCount")?;
+        for (node, rpt) in analysed.iter() {
+            for (kind, cnt) in rpt.failure_kind_cnt.iter() {
+                writeln!(tw, "{}\t{}\t{}", node, kind, cnt)?;
+            }
+        }
+        writeln!(tw)?;
+        writeln!(tw, "Node\tCount\tTop Errors")?;
+        for (node, rpt) in analysed.iter() {
+            for (msg, cnt) in rpt.get_top_errors(TOP_ERRORS) {
+                writeln!(tw, "{}\t{}\t{}", node, cnt, msg)?;
+            }
+        }
+        writeln!(tw)?;
+        tw.flush()
+    }
+
+    fn write_confirmation<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let confirm_report = if let Some(ref confirm_report) = self.confirm_report {
+            confirm_report
+        } else {
+            return Ok(());
+        };
+        let mut total = ConfirmReport::new();
+        let mut tw = TabWriter::new(&mut *out);
+        write!(tw, "Node\tCommitted\tMissing\tUnconfirmed")?;
+        for (name, _) in PERCENTILES.iter() {
+            write!(tw, "\tCommit{} (ms)", name)?;
+        }
+        writeln!(tw, "\tCommitMax (ms)")?;
+        for (node, rpt) in self.node.iter().zip(confirm_report.iter()) {
+            write!(tw, "{}\t", node)?;
+            rpt.write_row(&mut tw)?;
+            total.merge(rpt);
+        }
+        write!(tw, "Total\t")?;
+        total.write_row(&mut tw)?;
+        writeln!(tw)?;
+        tw.flush()
+    }
+
+    fn write_blocks<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let block_report = if let Some(ref block_report) = self.block_report {
+            block_report
+        } else {
+            return Ok(());
+        };
+        if block_report.blocks.is_empty() {
+            return Ok(());
+        }
+        let mut tw = TabWriter::new(&mut *out);
+        writeln!(tw, "Height\tTimestamp\tTxs\tInterval (ms)")?;
+        let mut last_timestamp = None;
+        for blk in block_report.blocks.iter() {
+            write!(tw, "{}\t{}\t{}\t", blk.height, blk.timestamp, blk.tx_cnt)?;
+            if let Some(last) = last_timestamp {
+                writeln!(tw, "{}", blk.timestamp.saturating_sub(last))?;
+            } else {
+                writeln!(tw, "-")?;
+            }
+            last_timestamp = Some(blk.timestamp);
+        }
+        writeln!(tw)?;
+        tw.flush()
+    }
+
+    fn write_balances<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let balance_report = if let Some(ref balance_report) = self.balance_report {
+            balance_report
+        } else {
+            return Ok(());
+        };
+        let bal = balance_report.analyse();
+        let mut tw = TabWriter::new(&mut *out);
+        writeln!(
+            tw,
+            "Account\tBefore\tSent\tReceived\tFee\tExpected\tAfter\tDiff"
+        )?;
+        for acc in bal.accounts.iter() {
+            writeln!(
+                tw,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                acc.address,
+                acc.before,
+                acc.sent,
+                acc.received,
+                acc.fee,
+                acc.expected,
+                acc.after,
+                acc.diff
+            )?;
+        }
+        writeln!(tw)?;
+        let conserved = match bal.conserved {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "unknown",
+        };
+        writeln!(
+            tw,
+            "Committed transfers: {}, total balance: {} -> {}, fees: {}, conserved: {}",
+            bal.committed, bal.total_before, bal.total_after, bal.total_fee, conserved
+        )?;
+        writeln!(tw)?;
+        tw.flush()
+    }
+
+    fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &self.summarize())?;
+        writeln!(out)
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let summary = self.summarize();
+        let mut wtr = csv::Writer::from_writer(out);
+        let mut row = CsvRow::new("general", "", None, &summary.stat, summary.confirm.as_ref());
+        row.chain_tps = summary.block.as_ref().map(|blk| blk.tps);
+        row.balance_mismatched = summary.balance.as_ref().map(|bal| bal.mismatched);
+        row.balance_conserved = summary.balance.as_ref().and_then(|bal| bal.conserved);
+        wtr.serialize(row)?;
+        write_csv_categories(&mut wtr, "", &summary.stat)?;
+        for phase in summary.phase.iter() {
+            let mut row = CsvRow::new("phase", "", None, &phase.stat, None);
+            row.phase = Some(&phase.name);
+            wtr.serialize(row)?;
+        }
+        for captain in summary.node.iter() {
+            wtr.serialize(CsvRow::new(
+                "node",
+                &captain.node,
+                None,
+                &captain.stat,
+                captain.confirm.as_ref(),
+            ))?;
+            write_csv_categories(&mut wtr, &captain.node, &captain.stat)?;
+            for soldier in captain.soldier.iter() {
+                wtr.serialize(CsvRow::new(
+                    "thread",
+                    &captain.node,
+                    Some(soldier.soldier_id),
+                    &soldier.stat,
+                    None,
+                ))?;
+            }
+        }
+        wtr.flush()
+    }
+
+    pub fn summarize(&self) -> GeneralSummary {
+        let mut total = SimpleReport::new();
+        let mut node = Vec::with_capacity(self.captain_report.len());
         for crpt in self.captain_report.iter() {
             let rpt = crpt.analyse();
-            let node = &self.node[crpt.captain_id];
-            write!(out, "{}:{}\t", node.host, node.port).unwrap();
-            write!(
-                out,
-                "{}\t{}\t{}\t{}\t{}\t",
-                rpt.success_cnt + rpt.failure_cnt + rpt.missing_cnt,
-                crpt.soldier_report.len(),
-                rpt.success_cnt,
-                rpt.failure_cnt,
-                rpt.missing_cnt
-            )
-            .unwrap();
-            let success_tm = rpt.get_success_tm();
-            let success_tm =
-                success_tm.as_secs() as f64 * 1e3 + f64::from(success_tm.subsec_nanos()) * 1e-6;
-            writeln!(out, "{:.6}", success_tm).unwrap();
-            total_success_cnt += rpt.success_cnt;
-        }
-        writeln!(out).unwrap();
-        out.flush().unwrap();
-        let total_cost_tm =
-            self.cost_tm.as_secs() as f64 * 1e3 + f64::from(self.cost_tm.subsec_nanos()) * 1e-6;
-        let tps = total_success_cnt as f64 / (total_cost_tm / 1e3);
-        println!("{:-24}Total Cost : {:12.3} ms", "", total_cost_tm);
-        println!("{:-24}Total Succ : {:12} tx", "", total_success_cnt);
-        println!("{:-24}    TPS    : {:12.3} tx/s", "", tps);
-        println!("----    ----    ----    ----    ----    ----    ----    ----    ----    ----");
+            let soldier = crpt
+                .soldier_report
+                .iter()
+                .map(|srpt| SoldierSummary {
+                    soldier_id: srpt.soldier_id,
+                    stat: StatSummary::new(srpt.ready_tm, srpt.cost_tm, &srpt.result),
+                })
+                .collect();
+            let confirm = self
+                .confirm_report
+                .as_ref()
+                .map(|confirm_report| ConfirmSummary::new(&confirm_report[crpt.captain_id]));
+            node.push(CaptainSummary {
+                node: self.node[crpt.captain_id].to_string(),
+                stat: StatSummary::new(crpt.ready_tm, crpt.cost_tm, &rpt),
+                confirm,
+                soldier,
+            });
+            total.merge(&rpt);
+        }
+        let stat = StatSummary::new(self.ready_tm, self.cost_tm, &total);
+        let tps = total.success_cnt as f64 / (duration_to_millis(self.get_measured_tm()) / 1e3);
+        let confirm = self.confirm_report.as_ref().map(|confirm_report| {
+            let mut total = ConfirmReport::new();
+            for rpt in confirm_report.iter() {
+                total.merge(rpt);
+            }
+            ConfirmSummary::new(&total)
+        });
+        let empty = SimpleReport::new();
+        let phase = self
+            .phases
+            .iter()
+            .enumerate()
+            .map(|(idx, phase)| {
+                let prpt = total.phase.get(idx).unwrap_or(&empty);
+                let phase_tm = self.get_phase_tm(idx);
+                PhaseSummary {
+                    name: phase.name.clone(),
+                    duration_ms: duration_to_millis(phase.duration),
+                    rate: phase.rate,
+                    rate_to: phase.rate_to,
+                    thread: phase.thread,
+                    tps: prpt.success_cnt as f64 / (duration_to_millis(phase_tm) / 1e3),
+                    stat: StatSummary::new(Duration::new(0, 0), phase_tm, prpt),
+                }
+            })
+            .collect();
+        let block = self.block_report.as_ref().map(BlockReport::analyse);
+        let balance = self.balance_report.as_ref().map(BalanceReport::analyse);
+        let delivery = self.delivery_report.as_ref().map(DeliveryReport::analyse);
+        GeneralSummary {
+            title: self.title.clone(),
+            warmup_tm_ms: duration_to_millis(self.warmup_tm),
+            tps,
+            stat,
+            confirm,
+            block,
+            balance,
+            delivery,
+            phase,
+            node,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeneralSummary {
+    pub title: String,
+    pub warmup_tm_ms: f64,
+    pub tps: f64,
+    pub stat: StatSummary,
+    pub confirm: Option<ConfirmSummary>,
+    pub block: Option<BlockSummary>,
+    pub balance: Option<BalanceSummary>,
+    pub delivery: Option<DeliverySummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub phase: Vec<PhaseSummary>,
+    pub node: Vec<CaptainSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhaseSummary {
+    pub name: String,
+    pub duration_ms: f64,
+    // Both are 0 if there is no fixed rate.
+    pub rate: usize,
+    pub rate_to: usize,
+    pub thread: Option<usize>,
+    pub tps: f64,
+    pub stat: StatSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaptainSummary {
+    pub node: String,
+    pub stat: StatSummary,
+    pub confirm: Option<ConfirmSummary>,
+    pub soldier: Vec<SoldierSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SoldierSummary {
+    pub soldier_id: usize,
+    pub stat: StatSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatSummary {
+    pub ready_tm_ms: f64,
+    pub cost_tm_ms: f64,
+    pub success: usize,
+    pub failure: usize,
+    pub success_tm_avg_ms: f64,
+    pub success_tm_p50_ms: f64,
+    pub success_tm_p90_ms: f64,
+    pub success_tm_p99_ms: f64,
+    pub success_tm_p999_ms: f64,
+    pub success_tm_max_ms: f64,
+    pub failure_kind: BTreeMap<String, usize>,
+    pub top_errors: Vec<(String, usize)>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub category: BTreeMap<String, StatSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchSummary>,
+}
+
+impl StatSummary {
+    fn new(rt: Duration, ct: Duration, rpt: &SimpleReport) -> Self {
+        let quantile = |q| duration_to_millis(rpt.get_success_tm_quantile(q));
+        StatSummary {
+            ready_tm_ms: duration_to_millis(rt),
+            cost_tm_ms: duration_to_millis(ct),
+            success: rpt.success_cnt,
+            failure: rpt.failure_cnt,
+            success_tm_avg_ms: duration_to_millis(rpt.get_success_tm()),
+            success_tm_p50_ms: quantile(0.5),
+            success_tm_p90_ms: quantile(0.9),
+            success_tm_p99_ms: quantile(0.99),
+            success_tm_p999_ms: quantile(0.999),
+            success_tm_max_ms: duration_to_millis(rpt.get_success_tm_max()),
+            failure_kind: rpt
+                .failure_kind_cnt
+                .iter()
+                .map(|(kind, cnt)| (kind.to_string(), *cnt))
+                .collect(),
+            top_errors: rpt.get_top_errors(TOP_ERRORS),
+            category: rpt
+                .category
+                .iter()
+                .map(|(category, crpt)| (category.clone(), StatSummary::new(rt, ct, crpt)))
+                .collect(),
+            batch: BatchSummary::new(rpt),
+        }
+    }
+
+    fn failure_of<F>(&self, filter: F) -> usize
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.failure_kind
+            .iter()
+            .filter(|(kind, _)| filter(kind))
+            .map(|(_, cnt)| cnt)
+            .sum()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchSummary {
+    pub count: usize,
+    pub size_avg: f64,
+    pub batch_tm_p50_ms: f64,
+    pub batch_tm_p90_ms: f64,
+    pub batch_tm_p99_ms: f64,
+    pub batch_tm_p999_ms: f64,
+    pub batch_tm_max_ms: f64,
+}
+
+impl BatchSummary {
+    fn new(rpt: &SimpleReport) -> Option<Self> {
+        if rpt.batch_cnt == 0 {
+            return None;
+        }
+        let quantile = |q| duration_to_millis(rpt.get_batch_tm_quantile(q));
+        Some(BatchSummary {
+            count: rpt.batch_cnt,
+            size_avg: (rpt.success_cnt + rpt.failure_cnt) as f64 / rpt.batch_cnt as f64,
+            batch_tm_p50_ms: quantile(0.5),
+            batch_tm_p90_ms: quantile(0.9),
+            batch_tm_p99_ms: quantile(0.99),
+            batch_tm_p999_ms: quantile(0.999),
+            batch_tm_max_ms: duration_to_millis(rpt.get_batch_tm_max()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfirmSummary {
+    pub committed: usize,
+    pub missing: usize,
+    pub unconfirmed: usize,
+    pub commit_tm_p50_ms: f64,
+    pub commit_tm_p90_ms: f64,
+    pub commit_tm_p99_ms: f64,
+    pub commit_tm_p999_ms: f64,
+    pub commit_tm_max_ms: f64,
+}
+
+impl ConfirmSummary {
+    fn new(rpt: &ConfirmReport) -> Self {
+        let quantile = |q| duration_to_millis(rpt.get_commit_tm_quantile(q));
+        ConfirmSummary {
+            committed: rpt.committed_cnt,
+            missing: rpt.missing_cnt,
+            unconfirmed: rpt.unconfirmed_cnt,
+            commit_tm_p50_ms: quantile(0.5),
+            commit_tm_p90_ms: quantile(0.9),
+            commit_tm_p99_ms: quantile(0.99),
+            commit_tm_p999_ms: quantile(0.999),
+            commit_tm_max_ms: duration_to_millis(rpt.get_commit_tm_max()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockSummary {
+    pub count: usize,
+    pub tx_total: usize,
+    pub tx_per_block_avg: f64,
+    pub tx_per_block_max: usize,
+    pub interval_avg_ms: f64,
+    pub interval_max_ms: u64,
+    // Transactions per second on chain, see `BlockReport::analyse`.
+    pub tps: f64,
+    pub blocks: Vec<BlockStat>,
+}
+
+// CSV has no nesting, so each row carries its scope and flattened stat.
+#[derive(Debug, Serialize)]
+struct CsvRow<'a> {
+    scope: &'a str,
+    node: &'a str,
+    thread: Option<usize>,
+    category: Option<&'a str>,
+    phase: Option<&'a str>,
+    ready_tm_ms: f64,
+    cost_tm_ms: f64,
+    success: usize,
+    failure: usize,
+    success_tm_avg_ms: f64,
+    success_tm_p50_ms: f64,
+    success_tm_p90_ms: f64,
+    success_tm_p99_ms: f64,
+    success_tm_p999_ms: f64,
+    success_tm_max_ms: f64,
+    failure_transport: usize,
+    failure_timeout: usize,
+    failure_rpc: usize,
+    failure_decode: usize,
+    failure_rejected: usize,
+    failure_other: usize,
+    committed: Option<usize>,
+    missing: Option<usize>,
+    unconfirmed: Option<usize>,
+    commit_tm_p50_ms: Option<f64>,
+    commit_tm_p90_ms: Option<f64>,
+    commit_tm_p99_ms: Option<f64>,
+    commit_tm_p999_ms: Option<f64>,
+    commit_tm_max_ms: Option<f64>,
+    chain_tps: Option<f64>,
+    balance_mismatched: Option<usize>,
+    balance_conserved: Option<bool>,
+    batch: Option<usize>,
+    batch_tm_p50_ms: Option<f64>,
+    batch_tm_p99_ms: Option<f64>,
+}
+
+fn write_csv_categories<W: Write>(
+    wtr: &mut csv::Writer<W>,
+    node: &str,
+    stat: &StatSummary,
+) -> io::Result<()> {
+    for (category, stat) in stat.category.iter() {
+        let mut row = CsvRow::new("category", node, None, stat, None);
+        row.category = Some(category);
+        wtr.serialize(row)?;
+    }
+    Ok(())
+}
+
+impl<'a> CsvRow<'a> {
+    fn new(
+        scope: &'a str,
+        node: &'a str,
+        thread: Option<usize>,
+        stat: &StatSummary,
+        confirm: Option<&ConfirmSummary>,
+    ) -> Self {
+        CsvRow {
+            scope,
+            node,
+            thread,
+            category: None,
+            phase: None,
+            ready_tm_ms: stat.ready_tm_ms,
+            cost_tm_ms: stat.cost_tm_ms,
+            success: stat.success,
+            failure: stat.failure,
+            success_tm_avg_ms: stat.success_tm_avg_ms,
+            success_tm_p50_ms: stat.success_tm_p50_ms,
+            success_tm_p90_ms: stat.success_tm_p90_ms,
+            success_tm_p99_ms: stat.success_tm_p99_ms,
+            success_tm_p999_ms: stat.success_tm_p999_ms,
+            success_tm_max_ms: stat.success_tm_max_ms,
+            failure_transport: stat.failure_of(|k| k == "Transport"),
+            failure_timeout: stat.failure_of(|k| k == "Timeout"),
+            failure_rpc: stat.failure_of(|k| k.starts_with("Rpc")),
+            failure_decode: stat.failure_of(|k| k == "Decode"),
+            failure_rejected: stat.failure_of(|k| k == "Rejected"),
+            failure_other: stat.failure_of(|k| k == "Other"),
+            committed: confirm.map(|c| c.committed),
+            missing: confirm.map(|c| c.missing),
+            unconfirmed: confirm.map(|c| c.unconfirmed),
+            commit_tm_p50_ms: confirm.map(|c| c.commit_tm_p50_ms),
+            commit_tm_p90_ms: confirm.map(|c| c.commit_tm_p90_ms),
+            commit_tm_p99_ms: confirm.map(|c| c.commit_tm_p99_ms),
+            commit_tm_p999_ms: confirm.map(|c| c.commit_tm_p999_ms),
+            commit_tm_max_ms: confirm.map(|c| c.commit_tm_max_ms),
+            chain_tps: None,
+            balance_mismatched: None,
+            balance_conserved: None,
+            batch: stat.batch.as_ref().map(|b| b.count),
+            batch_tm_p50_ms: stat.batch.as_ref().map(|b| b.batch_tm_p50_ms),
+            batch_tm_p99_ms: stat.batch.as_ref().map(|b| b.batch_tm_p99_ms),
+        }
     }
 }
 
@@ -96,10 +881,7 @@ impl CaptainReport {
     pub fn analyse(&self) -> SimpleReport {
         let mut rpt = SimpleReport::new();
         for srpt in self.soldier_report.iter() {
-            rpt.add(
-                srpt.success_tm_sum,
-                (srpt.success_cnt, srpt.failure_cnt, srpt.missing_cnt),
-            );
+            rpt.merge(&srpt.result);
         }
         rpt
     }
@@ -110,10 +892,7 @@ pub struct SoldierReport {
     soldier_id: usize,
     ready_tm: Duration,
     cost_tm: Duration,
-    success_tm_sum: Duration,
-    success_cnt: usize,
-    failure_cnt: usize,
-    missing_cnt: usize,
+    result: SimpleReport,
 }
 
 impl SoldierReport {
@@ -122,37 +901,175 @@ impl SoldierReport {
             soldier_id: id,
             ready_tm: rt,
             cost_tm: ct,
-            success_tm_sum: sr.success_tm_sum,
-            success_cnt: sr.success_cnt,
-            failure_cnt: sr.failure_cnt,
-            missing_cnt: sr.missing_cnt,
+            result: sr,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureKind {
+    // Failed to connect or lost the connection.
+    Transport,
+    Timeout,
+    // A JSON-RPC error object with its error code.
+    Rpc(i64),
+    // The response can not be decoded.
+    Decode,
+    // The transaction is rejected by the transaction pool.
+    Rejected,
+    Other,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FailureKind::Transport => write!(f, "Transport"),
+            FailureKind::Timeout => write!(f, "Timeout"),
+            FailureKind::Rpc(code) => write!(f, "Rpc({})", code),
+            FailureKind::Decode => write!(f, "Decode"),
+            FailureKind::Rejected => write!(f, "Rejected"),
+            FailureKind::Other => write!(f, "Other"),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl Failure {
+    pub fn new(kind: FailureKind, message: String) -> Self {
+        Failure { kind, message }
+    }
+}
+
+pub type Outcome = Result<(), Failure>;
+
 #[derive(Debug)]
 pub struct SimpleReport {
     success_tm_sum: Duration,
+    success_tm_hist: Histogram<u64>,
     success_cnt: usize,
     failure_cnt: usize,
-    missing_cnt: usize,
+    failure_kind_cnt: BTreeMap<FailureKind, usize>,
+    failure_msg_cnt: HashMap<String, usize>,
+    // Breaks down by categories, only when they are mixed.
+    category: BTreeMap<String, SimpleReport>,
+    // Breaks down by phases in order, only when a scenario is followed.
+    phase: Vec<SimpleReport>,
+    // Only when the calls are batched, each call is counted above with the latency of its batch.
+    batch_cnt: usize,
+    batch_tm_hist: Option<Histogram<u64>>,
 }
 
 impl SimpleReport {
     pub fn new() -> Self {
         SimpleReport {
             success_tm_sum: Duration::new(0, 0),
+            success_tm_hist: new_histogram(),
             success_cnt: 0,
             failure_cnt: 0,
-            missing_cnt: 0,
+            failure_kind_cnt: BTreeMap::new(),
+            failure_msg_cnt: HashMap::new(),
+            category: BTreeMap::new(),
+            phase: Vec::new(),
+            batch_cnt: 0,
+            batch_tm_hist: None,
+        }
+    }
+
+    pub fn add_batch(&mut self, bt: Duration) {
+        self.batch_cnt += 1;
+        self.batch_tm_hist
+            .get_or_insert_with(new_histogram)
+            .saturating_record(duration_to_micros(bt));
+    }
+
+    /// Adds a sample of a category, it is counted in both the category and the total.
+    pub fn add_of(&mut self, category: &str, st: Duration, outcome: Outcome) {
+        if !self.category.contains_key(category) {
+            self.category
+                .insert(category.to_owned(), SimpleReport::new());
+        }
+        self.category
+            .get_mut(category)
+            .unwrap()
+            .add(st, outcome.clone());
+        self.add(st, outcome);
+    }
+
+    /// Adds a sample to the phase only, it should be added to the total as well.
+    pub fn add_in_phase(&mut self, phase: usize, st: Duration, outcome: Outcome) {
+        while self.phase.len() <= phase {
+            self.phase.push(SimpleReport::new());
         }
+        self.phase[phase].add(st, outcome);
     }
 
-    pub fn add(&mut self, st: Duration, c: (usize, usize, usize)) {
-        self.success_tm_sum += st;
-        self.success_cnt += c.0;
-        self.failure_cnt += c.1;
-        self.missing_cnt += c.2;
+    pub fn add(&mut self, st: Duration, outcome: Outcome) {
+        match outcome {
+            Ok(()) => {
+                self.success_tm_sum += st;
+                self.success_tm_hist
+                    .saturating_record(duration_to_micros(st));
+                self.success_cnt += 1;
+            }
+            Err(failure) => {
+                self.failure_cnt += 1;
+                *self.failure_kind_cnt.entry(failure.kind).or_insert(0) += 1;
+                let mut msg = format!("{}: {}", failure.kind, failure.message);
+                if msg.len() > ERROR_MESSAGE_MAX_LEN {
+                    let mut end = ERROR_MESSAGE_MAX_LEN;
+                    while !msg.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    msg.truncate(end);
+                }
+                *self.failure_msg_cnt.entry(msg).or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &SimpleReport) {
+        self.success_tm_sum += other.success_tm_sum;
+        self.success_tm_hist.add(&other.success_tm_hist).unwrap();
+        self.success_cnt += other.success_cnt;
+        self.failure_cnt += other.failure_cnt;
+        for (kind, cnt) in other.failure_kind_cnt.iter() {
+            *self.failure_kind_cnt.entry(*kind).or_insert(0) += cnt;
+        }
+        for (msg, cnt) in other.failure_msg_cnt.iter() {
+            *self.failure_msg_cnt.entry(msg.clone()).or_insert(0) += cnt;
+        }
+        self.batch_cnt += other.batch_cnt;
+        if let Some(ref hist) = other.batch_tm_hist {
+            self.batch_tm_hist
+                .get_or_insert_with(new_histogram)
+                .add(hist)
+                .unwrap();
+        }
+        for (category, rpt) in other.category.iter() {
+            self.category
+                .entry(category.clone())
+                .or_insert_with(SimpleReport::new)
+                .merge(rpt);
+        }
+        for (idx, rpt) in other.phase.iter().enumerate() {
+            if self.phase.len() <= idx {
+                self.phase.push(SimpleReport::new());
+            }
+            self.phase[idx].merge(rpt);
+        }
+    }
+
+    pub fn get_success_cnt(&self) -> usize {
+        self.success_cnt
+    }
+
+    pub fn get_failure_cnt(&self) -> usize {
+        self.failure_cnt
     }
 
     pub fn get_success_tm(&self) -> Duration {
@@ -163,4 +1080,349 @@ impl SimpleReport {
             self.success_tm_sum / cnt
         }
     }
+
+    pub fn get_success_tm_quantile(&self, quantile: f64) -> Duration {
+        Duration::from_micros(self.success_tm_hist.value_at_quantile(quantile))
+    }
+
+    pub fn get_success_tm_max(&self) -> Duration {
+        Duration::from_micros(self.success_tm_hist.max())
+    }
+
+    pub fn get_batch_tm_quantile(&self, quantile: f64) -> Duration {
+        let micros = self
+            .batch_tm_hist
+            .as_ref()
+            .map(|hist| hist.value_at_quantile(quantile));
+        Duration::from_micros(micros.unwrap_or(0))
+    }
+
+    pub fn get_batch_tm_max(&self) -> Duration {
+        Duration::from_micros(self.batch_tm_hist.as_ref().map(Histogram::max).unwrap_or(0))
+    }
+
+    pub fn get_top_errors(&self, n: usize) -> Vec<(String, usize)> {
+        let mut errors = self
+            .failure_msg_cnt
+            .iter()
+            .map(|(msg, cnt)| (msg.clone(), *cnt))
+            .collect::<Vec<_>>();
+        errors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        errors.truncate(n);
+        errors
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfirmReport {
+    committed_cnt: usize,
+    // Not committed before the `valid_until_block` has passed.
+    missing_cnt: usize,
+    // Still pending when the tracking stopped.
+    unconfirmed_cnt: usize,
+    commit_tm_hist: Histogram<u64>,
+}
+
+impl ConfirmReport {
+    pub fn new() -> Self {
+        ConfirmReport {
+            committed_cnt: 0,
+            missing_cnt: 0,
+            unconfirmed_cnt: 0,
+            commit_tm_hist: new_histogram(),
+        }
+    }
+
+    pub fn add_committed(&mut self, ct: Duration) {
+        self.committed_cnt += 1;
+        self.commit_tm_hist
+            .saturating_record(duration_to_micros(ct));
+    }
+
+    pub fn add_missing(&mut self) {
+        self.missing_cnt += 1;
+    }
+
+    pub fn add_unconfirmed(&mut self) {
+        self.unconfirmed_cnt += 1;
+    }
+
+    pub fn merge(&mut self, other: &ConfirmReport) {
+        self.committed_cnt += other.committed_cnt;
+        self.missing_cnt += other.missing_cnt;
+        self.unconfirmed_cnt += other.unconfirmed_cnt;
+        self.commit_tm_hist.add(&other.commit_tm_hist).unwrap();
+    }
+
+    pub fn get_commit_tm_quantile(&self, quantile: f64) -> Duration {
+        Duration::from_micros(self.commit_tm_hist.value_at_quantile(quantile))
+    }
+
+    pub fn get_commit_tm_max(&self) -> Duration {
+        Duration::from_micros(self.commit_tm_hist.max())
+    }
+
+    fn write_row<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(
+            out,
+            "{}\t{}\t{}",
+            self.committed_cnt, self.missing_cnt, self.unconfirmed_cnt
+        )?;
+        for (_, quantile) in PERCENTILES.iter() {
+            let tm = self.get_commit_tm_quantile(*quantile);
+            write!(out, "\t{:.3}", duration_to_millis(tm))?;
+        }
+        writeln!(out, "\t{:.3}", duration_to_millis(self.get_commit_tm_max()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockStat {
+    pub height: u64,
+    // Milliseconds since the UNIX epoch.
+    pub timestamp: u64,
+    pub tx_cnt: usize,
+}
+
+#[derive(Debug)]
+pub struct BlockReport {
+    blocks: Vec<BlockStat>,
+}
+
+impl BlockReport {
+    pub fn new() -> Self {
+        BlockReport { blocks: Vec::new() }
+    }
+
+    pub fn add(&mut self, stat: BlockStat) {
+        self.blocks.push(stat);
+    }
+
+    /// The chain TPS only counts the blocks from the first non-empty block to
+    /// the last non-empty one, so idle blocks before sending and after all
+    /// transactions are committed are excluded.
+    pub fn analyse(&self) -> BlockSummary {
+        let count = self.blocks.len();
+        let tx_total = self.blocks.iter().map(|blk| blk.tx_cnt).sum::<usize>();
+        let tx_per_block_max = self.blocks.iter().map(|blk| blk.tx_cnt).max().unwrap_or(0);
+        let tx_per_block_avg = if count == 0 {
+            0.0
+        } else {
+            tx_total as f64 / count as f64
+        };
+        let intervals = self
+            .blocks
+            .windows(2)
+            .map(|w| w[1].timestamp.saturating_sub(w[0].timestamp))
+            .collect::<Vec<_>>();
+        let interval_max_ms = intervals.iter().cloned().max().unwrap_or(0);
+        let interval_avg_ms = if intervals.is_empty() {
+            0.0
+        } else {
+            intervals.iter().sum::<u64>() as f64 / intervals.len() as f64
+        };
+        let first = self.blocks.iter().position(|blk| blk.tx_cnt != 0);
+        let last = self.blocks.iter().rposition(|blk| blk.tx_cnt != 0);
+        let tps = match (first, last) {
+            (Some(first), Some(last)) if first < last => {
+                let txs = self.blocks[first + 1..=last]
+                    .iter()
+                    .map(|blk| blk.tx_cnt)
+                    .sum::<usize>();
+                let span = self.blocks[last]
+                    .timestamp
+                    .saturating_sub(self.blocks[first].timestamp);
+                if span == 0 {
+                    0.0
+                } else {
+                    txs as f64 / (span as f64 / 1e3)
+                }
+            }
+            _ => 0.0,
+        };
+        BlockSummary {
+            count,
+            tx_total,
+            tx_per_block_avg,
+            tx_per_block_max,
+            interval_avg_ms,
+            interval_max_ms,
+            tps,
+            blocks: self.blocks.clone(),
+        }
+    }
+}
+
+/// Latencies from the timestamps of blocks to their deliveries by subscriptions.
+///
+/// The timestamps come from the proposers, so the clocks should be synchronized.
+#[derive(Debug)]
+pub struct DeliveryReport {
+    delivered_cnt: usize,
+    // The delivered blocks which were not observed.
+    unmatched_cnt: usize,
+    delivery_tm_hist: Histogram<u64>,
+}
+
+impl DeliveryReport {
+    pub fn new() -> Self {
+        DeliveryReport {
+            delivered_cnt: 0,
+            unmatched_cnt: 0,
+            delivery_tm_hist: new_histogram(),
+        }
+    }
+
+    pub fn add_delivered(&mut self, millis: u64) {
+        self.delivered_cnt += 1;
+        self.delivery_tm_hist.saturating_record(millis * 1000);
+    }
+
+    pub fn add_unmatched(&mut self) {
+        self.unmatched_cnt += 1;
+    }
+
+    pub fn get_delivery_tm_quantile(&self, quantile: f64) -> Duration {
+        Duration::from_micros(self.delivery_tm_hist.value_at_quantile(quantile))
+    }
+
+    pub fn analyse(&self) -> DeliverySummary {
+        let quantile = |q| duration_to_millis(self.get_delivery_tm_quantile(q));
+        DeliverySummary {
+            delivered: self.delivered_cnt,
+            unmatched: self.unmatched_cnt,
+            delivery_tm_p50_ms: quantile(0.5),
+            delivery_tm_p90_ms: quantile(0.9),
+            delivery_tm_p99_ms: quantile(0.99),
+            delivery_tm_p999_ms: quantile(0.999),
+            delivery_tm_max_ms: duration_to_millis(Duration::from_micros(
+                self.delivery_tm_hist.max(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliverySummary {
+    pub delivered: usize,
+    pub unmatched: usize,
+    pub delivery_tm_p50_ms: f64,
+    pub delivery_tm_p90_ms: f64,
+    pub delivery_tm_p99_ms: f64,
+    pub delivery_tm_p999_ms: f64,
+    pub delivery_tm_max_ms: f64,
+}
+
+#[derive(Debug)]
+pub struct BalanceStat {
+    pub address: Address,
+    pub before: U256,
+    pub sent: U256,
+    pub received: U256,
+    // The fees of the sent transfers, none if failed to fetch them.
+    pub fee: Option<U256>,
+    // None if failed to fetch it.
+    pub after: Option<U256>,
+}
+
+impl BalanceStat {
+    fn expected(&self) -> U256 {
+        (self.before + self.received).saturating_sub(self.sent + self.fee.unwrap_or_default())
+    }
+}
+
+#[derive(Debug)]
+pub struct BalanceReport {
+    pub committed: usize,
+    pub accounts: Vec<BalanceStat>,
+}
+
+impl BalanceReport {
+    /// Amounts are printed in decimal strings, since they may overflow numbers in JSON.
+    ///
+    /// The total is conserved if the fees are all which the accounts lose, it is unknown if any
+    /// balance or fee is not fetched.
+    pub fn analyse(&self) -> BalanceSummary {
+        let mut total_before = U256::zero();
+        let mut total_after = U256::zero();
+        let mut total_fee = U256::zero();
+        let mut known = true;
+        let mut matched = 0;
+        let accounts = self
+            .accounts
+            .iter()
+            .map(|acc| {
+                let expected = acc.expected();
+                total_before += acc.before;
+                total_fee += acc.fee.unwrap_or_default();
+                known &= acc.fee.is_some() && acc.after.is_some();
+                let (after, diff) = if let Some(after) = acc.after {
+                    total_after += after;
+                    if after == expected {
+                        matched += 1;
+                    }
+                    let diff = if after >= expected {
+                        format!("+{}", after - expected)
+                    } else {
+                        format!("-{}", expected - after)
+                    };
+                    (after.to_string(), diff)
+                } else {
+                    ("-".to_owned(), "-".to_owned())
+                };
+                BalanceSummaryRow {
+                    address: format!("{:?}", acc.address),
+                    before: acc.before.to_string(),
+                    sent: acc.sent.to_string(),
+                    received: acc.received.to_string(),
+                    fee: acc
+                        .fee
+                        .map(|fee| fee.to_string())
+                        .unwrap_or_else(|| "-".to_owned()),
+                    expected: expected.to_string(),
+                    after,
+                    diff,
+                }
+            })
+            .collect::<Vec<_>>();
+        BalanceSummary {
+            committed: self.committed,
+            matched,
+            mismatched: accounts.len() - matched,
+            total_before: total_before.to_string(),
+            total_after: total_after.to_string(),
+            total_fee: total_fee.to_string(),
+            conserved: if known {
+                Some(total_after + total_fee == total_before)
+            } else {
+                None
+            },
+            accounts,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceSummary {
+    pub committed: usize,
+    pub matched: usize,
+    pub mismatched: usize,
+    pub total_before: String,
+    pub total_after: String,
+    pub total_fee: String,
+    // None if it is unknown.
+    pub conserved: Option<bool>,
+    pub accounts: Vec<BalanceSummaryRow>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BalanceSummaryRow {
+    pub address: String,
+    pub before: String,
+    pub sent: String,
+    pub received: String,
+    pub fee: String,
+    pub expected: String,
+    pub after: String,
+    pub diff: String,
 }
@@ -0,0 +1,132 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use cita_types::{Address, H256};
+use rand::{thread_rng, Rng};
+
+use crate::transaction::{fetch_block, fetch_contract_addresses, fetch_height};
+use crate::transport::Web3Client;
+
+// How many recent blocks to sample.
+const SAMPLE_BLOCKS: u64 = 100;
+// How many receipts to look for contracts.
+const SAMPLE_RECEIPTS: usize = 20;
+
+/// Real data on the chain, so each query hits something which exists.
+///
+/// It is collected once before the benchmark starts.
+#[derive(Debug, Clone)]
+pub struct ChainSample {
+    // The range of recent heights, both ends are included.
+    pub lowest: u64,
+    pub highest: u64,
+    pub block_hashes: Vec<H256>,
+    pub tx_hashes: Vec<H256>,
+    pub addresses: Vec<Address>,
+    pub contracts: Vec<Address>,
+}
+
+impl ChainSample {
+    pub fn collect(
+        web3: &Web3Client,
+        addresses: Vec<Address>,
+        contracts: Vec<Address>,
+    ) -> Result<Self, String> {
+        let highest =
+            fetch_height(web3).map_err(|err| format!("failed to fetch the height: {}", err))?;
+        let lowest = highest.saturating_sub(SAMPLE_BLOCKS - 1);
+        let mut block_hashes = Vec::new();
+        let mut tx_hashes = Vec::new();
+        for height in lowest..=highest {
+            match fetch_block(web3, height) {
+                Ok(block) => {
+                    block_hashes.push(block.hash);
+                    tx_hashes.extend(block.tx_hashes);
+                }
+                Err(err) => log::warn!("Failed to sample block#{}: {}", height, err),
+            }
+        }
+        let mut contracts = contracts;
+        for hash in tx_hashes.iter().rev().take(SAMPLE_RECEIPTS) {
+            match fetch_contract_addresses(web3, *hash) {
+                Ok(found) => contracts.extend(found),
+                Err(err) => log::warn!("Failed to sample receipt of {:?}: {}", hash, err),
+            }
+        }
+        contracts.sort();
+        contracts.dedup();
+        let mut addresses = addresses;
+        addresses.extend(contracts.iter().cloned());
+        addresses.sort();
+        addresses.dedup();
+        log::info!(
+            "Sampled blocks #{}..#{}: {} transactions, {} addresses and {} contracts.",
+            lowest,
+            highest,
+            tx_hashes.len(),
+            addresses.len(),
+            contracts.len()
+        );
+        Ok(Self {
+            lowest,
+            highest,
+            block_hashes,
+            tx_hashes,
+            addresses,
+            contracts,
+        })
+    }
+
+    /// Checks the data which the category requires.
+    pub fn check(&self, category: &str) -> Result<(), String> {
+        let missing = match category {
+            "getBlockByHash" if self.block_hashes.is_empty() => "blocks",
+            "getTransaction" | "getTransactionReceipt" | "getTransactionProof"
+                if self.tx_hashes.is_empty() =>
+            {
+                "transactions"
+            }
+            "getTransactionCount" | "getBalance" if self.addresses.is_empty() => "addresses",
+            "getCode" | "getAbi" | "getStateProof" if self.contracts.is_empty() => "contracts",
+            _ => return Ok(()),
+        };
+        let hint = if missing == "contracts" {
+            ", set \"contract\" to query it"
+        } else {
+            ""
+        };
+        Err(format!(
+            "no {} are found in recent blocks for {}{}",
+            missing, category, hint
+        ))
+    }
+
+    pub fn height(&self) -> u64 {
+        thread_rng().gen_range(self.lowest, self.highest + 1)
+    }
+
+    pub fn block_hash(&self) -> H256 {
+        pick(&self.block_hashes)
+    }
+
+    pub fn tx_hash(&self) -> H256 {
+        pick(&self.tx_hashes)
+    }
+
+    pub fn address(&self) -> Address {
+        pick(&self.addresses)
+    }
+
+    pub fn contract(&self) -> Address {
+        pick(&self.contracts)
+    }
+}
+
+fn pick<T: Copy>(items: &[T]) -> T {
+    items[thread_rng().gen_range(0, items.len())]
+}
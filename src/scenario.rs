@@ -0,0 +1,169 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    phase: Vec<PhaseEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct PhaseEntry {
+    name: Option<String>,
+    duration: String,
+    rate: Option<usize>,
+    rate_to: Option<usize>,
+    thread: Option<usize>,
+}
+
+/// A part of the scenario, the rate changes linearly from `rate` to `rate_to`.
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub name: String,
+    pub duration: Duration,
+    // Both are 0 if there is no fixed rate.
+    pub rate: usize,
+    pub rate_to: usize,
+    // How many threads of each node are active, all of them if none is set.
+    pub thread: Option<usize>,
+}
+
+impl Phase {
+    pub fn is_rated(&self) -> bool {
+        self.rate != 0 || self.rate_to != 0
+    }
+
+    /// The time from the offset in the phase to the next slot, none if the rate is not fixed.
+    fn next_slot(&self, offset: Duration) -> Option<Duration> {
+        if !self.is_rated() {
+            return None;
+        }
+        let total = duration_to_secs(self.duration);
+        let t = duration_to_secs(offset);
+        let (rate, rate_to) = (self.rate as f64, self.rate_to as f64);
+        let slope = if total == 0.0 {
+            0.0
+        } else {
+            (rate_to - rate) / total
+        };
+        let current = rate + slope * t;
+        // Solve `current * dt + slope * dt^2 / 2 = 1` for the area of one slot.
+        let dt = if slope == 0.0 {
+            1.0 / current
+        } else {
+            let delta = current * current + 2.0 * slope;
+            if delta < 0.0 {
+                // The rate drops to zero before the next slot.
+                total - t
+            } else {
+                (delta.sqrt() - current) / slope
+            }
+        };
+        Some(secs_to_duration(dt.max(0.0)))
+    }
+}
+
+/// Phases which are followed one by one, such as ramp up, hold, spike and cool down.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub path: String,
+    pub phases: Vec<Phase>,
+}
+
+impl Scenario {
+    pub fn duration(&self) -> Duration {
+        self.phases
+            .iter()
+            .fold(Duration::new(0, 0), |sum, phase| sum + phase.duration)
+    }
+
+    /// The max number of active threads of each node in all phases.
+    pub fn max_thread(&self, default: usize) -> usize {
+        self.phases
+            .iter()
+            .map(|phase| phase.thread.unwrap_or(default))
+            .max()
+            .unwrap_or(default)
+    }
+
+    /// Returns the index of the phase at the offset from the start, and the offset in it.
+    pub fn phase_at(&self, offset: Duration) -> Option<(usize, Duration)> {
+        let mut begin = Duration::new(0, 0);
+        for (idx, phase) in self.phases.iter().enumerate() {
+            if offset < begin + phase.duration {
+                return Some((idx, offset - begin));
+            }
+            begin += phase.duration;
+        }
+        None
+    }
+
+    /// Returns the offset of the slot after the one at the offset, none if the rate is not fixed.
+    pub fn next_slot(&self, offset: Duration) -> Option<Duration> {
+        let (idx, offset_in_phase) = self.phase_at(offset)?;
+        let phase = &self.phases[idx];
+        let dt = phase.next_slot(offset_in_phase)?;
+        // Never skip into the next phase by a low rate.
+        let rest = phase.duration - offset_in_phase;
+        Some(offset + if dt < rest { dt } else { rest })
+    }
+}
+
+fn duration_to_secs(d: Duration) -> f64 {
+    d.as_secs() as f64 + f64::from(d.subsec_nanos()) * 1e-9
+}
+
+fn secs_to_duration(secs: f64) -> Duration {
+    Duration::from_nanos((secs * 1e9) as u64)
+}
+
+/// Loads phases from a TOML file, each `[[phase]]` has a `duration`, and optionally a `name`,
+/// a `rate` (req/s) which ramps to `rate-to`, and the number of active threads in `thread`.
+pub fn load_scenario(path: &str) -> Result<Scenario, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("failed to read the scenario {}: {}", path, err))?;
+    let file = toml::from_str::<ScenarioFile>(&content)
+        .map_err(|err| format!("failed to parse the scenario {}: {}", path, err))?;
+    if file.phase.is_empty() {
+        return Err(format!("no phase is in the scenario {}", path));
+    }
+    let mut phases = Vec::with_capacity(file.phase.len());
+    for (idx, entry) in file.phase.into_iter().enumerate() {
+        let name = entry.name.unwrap_or_else(|| format!("phase{}", idx + 1));
+        let duration: Duration = entry
+            .duration
+            .parse::<humantime::Duration>()
+            .map_err(|err| format!("invalid duration of the phase \"{}\": {}", name, err))?
+            .into();
+        if duration == Duration::new(0, 0) {
+            return Err(format!("the duration of the phase \"{}\" is zero", name));
+        }
+        if entry.thread == Some(0) {
+            return Err(format!("the phase \"{}\" has no active thread", name));
+        }
+        let rate = entry.rate.unwrap_or(0);
+        let rate_to = entry.rate_to.unwrap_or(rate);
+        phases.push(Phase {
+            name,
+            duration,
+            rate,
+            rate_to,
+            thread: entry.thread,
+        });
+    }
+    Ok(Scenario {
+        path: path.to_owned(),
+        phases,
+    })
+}
@@ -0,0 +1,540 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::ToSocketAddrs;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cita_web3::web3::{
+    futures::{
+        future::{self, Either},
+        sync::{mpsc, oneshot},
+        Future, Sink, Stream,
+    },
+    helpers, rpc, BatchTransport, Error as Web3Error, ErrorKind as Web3ErrorKind, RequestId,
+    Transport,
+};
+use hyper::client::HttpConnector;
+use hyper::header::{ContentLength, ContentType};
+use hyper::{Client, Method, Request, Uri};
+use rustls::internal::pemfile;
+use rustls::{
+    Certificate, ClientConfig, NoClientAuth, PrivateKey, RootCertStore, ServerCertVerified,
+    ServerCertVerifier, ServerConfig, TLSError,
+};
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::{Handle, Timeout};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+use tokio_service::Service;
+use webpki::DNSNameRef;
+use websocket::{ClientBuilder, OwnedMessage};
+
+use crate::config::Node;
+
+// How long to wait for each response, if no timeout is set.
+const TIMEOUT_SECS: u64 = 30;
+// The largest HTTP response to receive.
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Settings of TLS for the https and wss protocols.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    // Trust the certificates in the file instead of the built-in roots.
+    pub ca_file: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    // Skip the verification of the server certificates.
+    pub insecure: bool,
+    // The server name to send and verify, instead of the host of the node.
+    pub sni: Option<String>,
+    // How long to wait for each response.
+    pub timeout: Option<Duration>,
+}
+
+impl TlsOptions {
+    pub fn client_config(&self) -> Result<ClientConfig, String> {
+        let mut config = ClientConfig::new();
+        if let Some(ref path) = self.ca_file {
+            let mut reader = open(path)?;
+            let (added, _) = config
+                .root_store
+                .add_pem_file(&mut reader)
+                .map_err(|_| format!("the CA file {} is malformed", path))?;
+            if added == 0 {
+                return Err(format!("no certificates are found in the CA file {}", path));
+            }
+        } else {
+            config
+                .root_store
+                .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        }
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let (certs, key) = read_cert_and_key(cert_path, key_path)?;
+                config.set_single_client_cert(certs, key);
+            }
+            (None, None) => {}
+            _ => return Err("both the client certificate and the key are required".to_owned()),
+        }
+        if self.insecure {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(NoVerification));
+        }
+        Ok(config)
+    }
+}
+
+/// The config of the mock node to serve https and wss with the certificate and the key.
+pub fn server_config(cert_path: &str, key_path: &str) -> Result<ServerConfig, String> {
+    let (certs, key) = read_cert_and_key(cert_path, key_path)?;
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .map_err(|err| format!("{} does not fit {}: {}", key_path, cert_path, err))?;
+    Ok(config)
+}
+
+fn open(path: &str) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| format!("failed to open {}: {}", path, err))
+}
+
+// Reads the certificate chain, and the first key in the key file.
+fn read_cert_and_key(
+    cert_path: &str,
+    key_path: &str,
+) -> Result<(Vec<Certificate>, PrivateKey), String> {
+    let certs =
+        pemfile::certs(&mut open(cert_path)?).map_err(|_| format!("{} is malformed", cert_path))?;
+    if certs.is_empty() {
+        return Err(format!("no certificates are found in {}", cert_path));
+    }
+    let mut keys = read_keys(key_path)?;
+    if keys.is_empty() {
+        return Err(format!("no private keys are found in {}", key_path));
+    }
+    Ok((certs, keys.remove(0)))
+}
+
+// Reads the PKCS#8 keys, or the RSA keys if there is none.
+fn read_keys(path: &str) -> Result<Vec<PrivateKey>, String> {
+    let malformed = |_| format!("{} is malformed", path);
+    let keys = pemfile::pkcs8_private_keys(&mut open(path)?).map_err(malformed)?;
+    if !keys.is_empty() {
+        return Ok(keys);
+    }
+    pemfile::rsa_private_keys(&mut open(path)?).map_err(malformed)
+}
+
+// Only for test clusters with self-signed certificates.
+struct NoVerification;
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        _presented_certs: &[Certificate],
+        _dns_name: DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+type Pending = oneshot::Sender<Result<rpc::Response, Web3Error>>;
+
+// The requests waiting for responses through WebSocket, it is taken once the connection is closed.
+type PendingMap = Rc<RefCell<Option<HashMap<RequestId, Pending>>>>;
+
+/// A transport over TLS, by HTTP/1.1 or WebSocket.
+///
+/// The requests are sent on the event loop, a request fails as `TimedOut` if there is no
+/// response in time.
+#[derive(Clone)]
+pub struct TlsTransport {
+    url: String,
+    id: Arc<AtomicUsize>,
+    requests: mpsc::UnboundedSender<(rpc::Request, Pending)>,
+}
+
+impl fmt::Debug for TlsTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TlsTransport({})", self.url)
+    }
+}
+
+impl TlsTransport {
+    pub fn with_event_loop(
+        config: Arc<ClientConfig>,
+        options: &TlsOptions,
+        node: &Node,
+        websocket: bool,
+        handle: &Handle,
+    ) -> Result<Self, Web3Error> {
+        let server_name = options.sni.as_ref().unwrap_or(&node.host).to_owned();
+        if DNSNameRef::try_from_ascii_str(&server_name).is_err() {
+            let msg = format!(
+                "[{}] is not a valid server name, set the SNI for TLS",
+                server_name
+            );
+            return Err(Web3ErrorKind::Transport(msg).into());
+        }
+        let scheme = if websocket { "wss" } else { "https" };
+        let url = format!("{}://{}:{}/", scheme, node.host, node.port);
+        let timeout = options
+            .timeout
+            .unwrap_or_else(|| Duration::from_secs(TIMEOUT_SECS));
+        let tls = TlsConnector::from(config);
+        let (requests, receiver) = mpsc::unbounded();
+        if websocket {
+            let addr = (node.host.as_str(), node.port)
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "the host is not resolved")
+                })?;
+            let builder = ClientBuilder::new(&url)
+                .map_err(|err| Web3ErrorKind::Transport(format!("{}: {}", url, err)))?;
+            let connecting = TcpStream::connect(&addr, handle)
+                .and_then(move |socket| {
+                    // It has been checked above.
+                    let dns_name = DNSNameRef::try_from_ascii_str(&server_name).unwrap();
+                    tls.connect(dns_name, socket)
+                })
+                .map_err(Web3Error::from)
+                .and_then(|stream| {
+                    builder.async_connect_on(stream).map_err(|err| {
+                        Web3Error::from(Web3ErrorKind::Transport(format!("{}", err)))
+                    })
+                });
+            let loop_handle = handle.clone();
+            let task = with_timeout(connecting, timeout, handle).then(move |result| match result {
+                Ok((client, _)) => {
+                    Either::A(serve_websocket(client, timeout, receiver, &loop_handle))
+                }
+                Err(err) => Either::B(refuse(receiver, &err)),
+            });
+            handle.spawn(task);
+        } else {
+            let uri: Uri = url
+                .parse()
+                .map_err(|err| Web3ErrorKind::Transport(format!("{}: {}", url, err)))?;
+            let mut http = HttpConnector::new(1, handle);
+            http.enforce_http(false);
+            let connector = HttpsConnector {
+                http,
+                tls,
+                server_name,
+            };
+            let client = Client::configure().connector(connector).build(handle);
+            let loop_handle = handle.clone();
+            let task = receiver.for_each(move |(request, pending)| {
+                let body = helpers::to_string(&request);
+                let mut req: Request = Request::new(Method::Post, uri.clone());
+                req.headers_mut().set(ContentType::json());
+                req.headers_mut().set(ContentLength(body.len() as u64));
+                req.set_body(body);
+                let response = client
+                    .request(req)
+                    .map_err(hyper_error)
+                    .and_then(read_body)
+                    .and_then(|body| helpers::to_response_from_slice(&body));
+                loop_handle.spawn(
+                    with_timeout(response, timeout, &loop_handle).then(|result| {
+                        let _ = pending.send(result);
+                        Ok(())
+                    }),
+                );
+                Ok(())
+            });
+            handle.spawn(task);
+        }
+        Ok(Self {
+            url,
+            id: Arc::new(AtomicUsize::new(1)),
+            requests,
+        })
+    }
+
+    fn post(
+        &self,
+        request: rpc::Request,
+    ) -> Box<Future<Item = rpc::Response, Error = Web3Error> + Send> {
+        let (pending, response) = oneshot::channel();
+        if self.requests.unbounded_send((request, pending)).is_err() {
+            return Box::new(future::err(closed()));
+        }
+        Box::new(response.then(|result| result.unwrap_or_else(|_| Err(closed()))))
+    }
+}
+
+// Connects by TCP for hyper, then does the handshake of TLS.
+struct HttpsConnector {
+    http: HttpConnector,
+    tls: TlsConnector,
+    server_name: String,
+}
+
+impl Service for HttpsConnector {
+    type Request = Uri;
+    type Response = TlsStream<TcpStream>;
+    type Error = io::Error;
+    type Future = Box<Future<Item = Self::Response, Error = io::Error>>;
+
+    fn call(&self, uri: Uri) -> Self::Future {
+        let tls = self.tls.clone();
+        let server_name = self.server_name.clone();
+        Box::new(self.http.call(uri).and_then(move |socket| {
+            // It has been checked when the transport is built.
+            let dns_name = DNSNameRef::try_from_ascii_str(&server_name).unwrap();
+            tls.connect(dns_name, socket)
+        }))
+    }
+}
+
+fn read_body(response: hyper::Response) -> impl Future<Item = Vec<u8>, Error = Web3Error> {
+    if !response.status().is_success() {
+        let msg = format!("unexpected status code {}", response.status());
+        return Either::A(future::err(Web3ErrorKind::Transport(msg).into()));
+    }
+    let body = response
+        .body()
+        .map_err(hyper_error)
+        .fold(Vec::new(), |mut body, chunk| {
+            if body.len() + chunk.len() > MAX_MESSAGE_BYTES {
+                let msg = format!("the response is larger than {} bytes", MAX_MESSAGE_BYTES);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
+            }
+            body.extend_from_slice(&chunk);
+            Ok::<_, Web3Error>(body)
+        });
+    Either::B(body)
+}
+
+fn hyper_error(err: hyper::Error) -> Web3Error {
+    match err {
+        hyper::Error::Io(err) => err.into(),
+        err => Web3ErrorKind::Transport(format!("{}", err)).into(),
+    }
+}
+
+// Sends the requests through the connection and answers each one with the response of the
+// same id.
+fn serve_websocket<C, E>(
+    client: C,
+    timeout: Duration,
+    receiver: mpsc::UnboundedReceiver<(rpc::Request, Pending)>,
+    handle: &Handle,
+) -> impl Future<Item = (), Error = ()>
+where
+    C: Stream<Item = OwnedMessage, Error = E> + Sink<SinkItem = OwnedMessage, SinkError = E>,
+    C: 'static,
+{
+    let (sink, stream) = client.split();
+    let (messages, outgoing) = mpsc::unbounded();
+    handle.spawn(
+        sink.sink_map_err(|_| ())
+            .send_all(outgoing)
+            .then(|_| Ok(())),
+    );
+    let pending: PendingMap = Rc::new(RefCell::new(Some(HashMap::new())));
+    let reader = {
+        let pending = pending.clone();
+        let messages = messages.clone();
+        stream
+            .map_err(|_| ())
+            .for_each(move |message| {
+                match message {
+                    OwnedMessage::Text(text) => respond(&pending, text.as_bytes()),
+                    OwnedMessage::Binary(data) => respond(&pending, &data),
+                    OwnedMessage::Ping(data) => {
+                        let _ = messages.unbounded_send(OwnedMessage::Pong(data));
+                    }
+                    OwnedMessage::Pong(_) => {}
+                    OwnedMessage::Close(_) => return Err(()),
+                }
+                Ok(())
+            })
+            .then(move |_| {
+                if let Some(pending) = pending.borrow_mut().take() {
+                    for (_, tx) in pending {
+                        let _ = tx.send(Err(closed()));
+                    }
+                }
+                Ok(())
+            })
+    };
+    handle.spawn(reader);
+    let handle = handle.clone();
+    receiver.for_each(move |(request, tx)| {
+        let id = match request_id(&request) {
+            Some(id) => id,
+            None => {
+                let msg = "the request has no numeric id".to_owned();
+                let _ = tx.send(Err(Web3ErrorKind::Transport(msg).into()));
+                return Ok(());
+            }
+        };
+        match *pending.borrow_mut() {
+            Some(ref mut pending) => pending.insert(id, tx),
+            None => {
+                let _ = tx.send(Err(closed()));
+                return Ok(());
+            }
+        };
+        let _ = messages.unbounded_send(OwnedMessage::Text(helpers::to_string(&request)));
+        let pending = pending.clone();
+        let expired = move |err: Web3Error| {
+            if let Some(tx) = pending.borrow_mut().as_mut().and_then(|p| p.remove(&id)) {
+                let _ = tx.send(Err(err));
+            }
+        };
+        match Timeout::new(timeout, &handle) {
+            Ok(timer) => handle.spawn(timer.then(move |_| {
+                expired(timed_out());
+                Ok(())
+            })),
+            Err(err) => expired(err.into()),
+        }
+        Ok(())
+    })
+}
+
+// Notifications and the responses which come too late are dropped.
+fn respond(pending: &PendingMap, data: &[u8]) {
+    if let Ok(response) = helpers::to_response_from_slice(data) {
+        let tx = response_id(&response)
+            .and_then(|id| pending.borrow_mut().as_mut().and_then(|p| p.remove(&id)));
+        if let Some(tx) = tx {
+            let _ = tx.send(Ok(response));
+        }
+    }
+}
+
+// Fails every request as the connection did.
+fn refuse(
+    receiver: mpsc::UnboundedReceiver<(rpc::Request, Pending)>,
+    err: &Web3Error,
+) -> impl Future<Item = (), Error = ()> {
+    let kind = match *err.kind() {
+        Web3ErrorKind::Io(ref err) => err.kind(),
+        _ => io::ErrorKind::Other,
+    };
+    let msg = format!("failed to connect: {}", err);
+    receiver.for_each(move |(_, tx)| {
+        let _ = tx.send(Err(io::Error::new(kind, msg.clone()).into()));
+        Ok(())
+    })
+}
+
+// The id of a batch is the one of its first call.
+fn request_id(request: &rpc::Request) -> Option<RequestId> {
+    let call = match *request {
+        rpc::Request::Single(ref call) => call,
+        rpc::Request::Batch(ref calls) => calls.first()?,
+    };
+    match *call {
+        rpc::Call::MethodCall(ref call) => to_request_id(&call.id),
+        _ => None,
+    }
+}
+
+fn response_id(response: &rpc::Response) -> Option<RequestId> {
+    let output = match *response {
+        rpc::Response::Single(ref output) => output,
+        rpc::Response::Batch(ref outputs) => outputs.first()?,
+    };
+    match *output {
+        rpc::Output::Success(ref success) => to_request_id(&success.id),
+        rpc::Output::Failure(ref failure) => to_request_id(&failure.id),
+    }
+}
+
+fn to_request_id(id: &rpc::Id) -> Option<RequestId> {
+    match *id {
+        rpc::Id::Num(id) => Some(id as RequestId),
+        _ => None,
+    }
+}
+
+fn with_timeout<F>(
+    future: F,
+    timeout: Duration,
+    handle: &Handle,
+) -> Box<Future<Item = F::Item, Error = Web3Error>>
+where
+    F: Future<Error = Web3Error> + 'static,
+    F::Item: 'static,
+{
+    let timer = match Timeout::new(timeout, handle) {
+        Ok(timer) => timer,
+        Err(err) => return Box::new(future::err(err.into())),
+    };
+    Box::new(future.select2(timer).then(|result| match result {
+        Ok(Either::A((item, _))) => Ok(item),
+        Err(Either::A((err, _))) => Err(err),
+        Ok(Either::B(_)) => Err(timed_out()),
+        Err(Either::B((err, _))) => Err(err.into()),
+    }))
+}
+
+fn timed_out() -> Web3Error {
+    io::Error::new(io::ErrorKind::TimedOut, "no response in time").into()
+}
+
+fn closed() -> Web3Error {
+    Web3ErrorKind::Transport("the connection is closed".to_owned()).into()
+}
+
+impl Transport for TlsTransport {
+    type Out = Box<Future<Item = rpc::Value, Error = Web3Error> + Send>;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        let id = self.id.fetch_add(1, Ordering::SeqCst);
+        (id, helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, _id: RequestId, request: rpc::Call) -> Self::Out {
+        Box::new(
+            self.post(rpc::Request::Single(request))
+                .and_then(|response| match response {
+                    rpc::Response::Single(output) => helpers::to_result_from_output(output),
+                    _ => Err(Web3ErrorKind::InvalidResponse("expected a single one".into()).into()),
+                }),
+        )
+    }
+}
+
+impl BatchTransport for TlsTransport {
+    type Batch = Box<Future<Item = Vec<Result<rpc::Value, Web3Error>>, Error = Web3Error> + Send>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        let calls = requests.into_iter().map(|(_, call)| call).collect();
+        Box::new(self.post(rpc::Request::Batch(calls)).and_then(|response| {
+            match response {
+                rpc::Response::Batch(outputs) => Ok(outputs
+                    .into_iter()
+                    .map(helpers::to_result_from_output)
+                    .collect()),
+                _ => Err(Web3ErrorKind::InvalidResponse("expected a batch".into()).into()),
+            }
+        }))
+    }
+}
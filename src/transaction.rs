@@ -6,160 +6,853 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use std::io;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, Instant};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use cita_types::{Address, H256, U256};
 use cita_web3::{
     api::Cita,
-    types,
-    web3::{futures::Future, transports::Http, ErrorKind as Web3ErrorKind, Web3},
+    types::{self, request::JsonRpcRequest},
+    web3::{
+        futures::{future, Future, Stream},
+        BatchTransport, Error as Web3Error, ErrorKind as Web3ErrorKind, Transport,
+    },
 };
+use rustc_hex::FromHex;
+use serde_json::Value;
+
+use crate::abi::encode_call;
+use crate::account::PrivateKey;
+use crate::config::{AppConfig, Node};
+use crate::ledger::Transfer;
+use crate::observer::Observer;
+use crate::replay::ReplayTx;
+use crate::report::{Failure, FailureKind, Outcome};
+use crate::sample::ChainSample;
+use crate::transport::{AnyTransport, Web3Client};
+use crate::workload::StepOutcome;
 
 pub const JSONRPC_METHODS: &[&str] = &[
     "peerCount",
     "blockNumber",
     "getMetaData",
     "sendRawTransaction",
+    "deployContract",
+    "callContract",
+    "transfer",
+    "call",
+    "newBlockFilter",
+    "getBlockByNumber",
+    "getBlockByHash",
+    "getTransaction",
+    "getTransactionReceipt",
+    "getTransactionCount",
+    "getBalance",
+    "getCode",
+    "getAbi",
+    "getLogs",
+    "getTransactionProof",
+    "getBlockHeader",
+    "getStateProof",
+    "getVersion",
+];
+
+// Subscribes to the notifications, which are pushed through a WebSocket connection.
+const SUBSCRIBE_METHOD: &str = "subscribe";
+const NEW_BLOCK_EVENT: &str = "newHeads";
+// How long to wait for a pushed block, longer than the interval of blocks.
+const NOTIFICATION_TIMEOUT_SECS: u64 = 30;
+// The error code which CITA uses when a transaction is not accepted by the pool.
+const TX_REJECTED_CODE: i64 = -32006;
+// The system contract which manages the price of the quota.
+const PRICE_MANAGER: [u8; 20] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0x02, 0x00, 0x10,
 ];
 
-type Web3Http = Web3<Http>;
-type RespStat = (Duration, (usize, usize, usize));
+/// What to put into the transactions, or the read-only calls.
+#[derive(Debug, Clone)]
+pub struct TxParams {
+    pub to: Option<Address>,
+    pub data: Vec<u8>,
+    pub quota: u64,
+    pub value: U256,
+    // Transfer to one of them in random, except the sender itself.
+    pub receivers: Vec<Address>,
+}
+
+impl TxParams {
+    pub fn new(category: &str, c: &AppConfig) -> Self {
+        let (to, data) = match category {
+            "deployContract" => (None, c.bytecode.clone().unwrap_or_default()),
+            "callContract" | "call" => (c.contract, c.call_data.clone().unwrap_or_default()),
+            _ => (None, Vec::new()),
+        };
+        let (value, receivers) = if category == "transfer" {
+            let mut receivers = c.key.iter().map(PrivateKey::address).collect::<Vec<_>>();
+            receivers.sort();
+            receivers.dedup();
+            (c.value.into(), receivers)
+        } else {
+            (U256::zero(), Vec::new())
+        };
+        Self {
+            to,
+            data,
+            quota: c.quota,
+            value,
+            receivers,
+        }
+    }
+}
+
+/// Whether the category sends transactions.
+pub fn is_tx_category(category: &str) -> bool {
+    match category {
+        "sendRawTransaction" | "deployContract" | "callContract" | "transfer" => true,
+        _ => false,
+    }
+}
+
+/// Whether the category queries the data sampled from the chain.
+pub fn is_sample_category(category: &str) -> bool {
+    match category {
+        "getBlockByNumber"
+        | "getBlockByHash"
+        | "getTransaction"
+        | "getTransactionReceipt"
+        | "getTransactionCount"
+        | "getBalance"
+        | "getCode"
+        | "getAbi"
+        | "getLogs"
+        | "getTransactionProof"
+        | "getBlockHeader"
+        | "getStateProof" => true,
+        _ => false,
+    }
+}
+
+impl From<Web3Error> for Failure {
+    fn from(err: Web3Error) -> Self {
+        let message = err.to_string();
+        let kind = match *err.kind() {
+            Web3ErrorKind::Rpc(ref e) if e.code.code() == TX_REJECTED_CODE => FailureKind::Rejected,
+            Web3ErrorKind::Rpc(ref e) => FailureKind::Rpc(e.code.code()),
+            Web3ErrorKind::InvalidResponse(_) | Web3ErrorKind::Decoder(_) => FailureKind::Decode,
+            Web3ErrorKind::Io(ref e) if e.kind() == io::ErrorKind::TimedOut => FailureKind::Timeout,
+            Web3ErrorKind::Io(_) | Web3ErrorKind::Transport(_) | Web3ErrorKind::Unreachable => {
+                FailureKind::Transport
+            }
+            _ => FailureKind::Other,
+        };
+        Failure::new(kind, message)
+    }
+}
 
-pub fn get_func_core<'a>(category: &str) -> Box<Fn(&'a Web3Http) -> RespStat> {
+pub fn get_func_core(
+    category: &str,
+    node: &Node,
+    observer: Arc<Observer>,
+    key: PrivateKey,
+    tx_params: TxParams,
+    sample: Option<Arc<ChainSample>>,
+    chain_id: Option<U256>,
+) -> Box<Fn(&Web3Client) -> Prepared> {
+    if is_sample_category(category) {
+        let sample = sample.expect("the chain is not sampled");
+        return gen_sample_query(category, sample);
+    }
     match category {
         "peerCount" => Box::new(peer_count),
         "blockNumber" => Box::new(block_number),
         "getMetaData" => Box::new(get_meta_data),
-        "sendRawTransaction" => gen_send_raw_transaction(),
+        "sendRawTransaction" | "deployContract" | "callContract" | "transfer" => {
+            let chain_id = chain_id.expect("the chain id is not fetched");
+            gen_send_raw_transaction(node, observer, key, tx_params, chain_id)
+        }
+        "call" => gen_call(tx_params),
+        "newBlockFilter" => gen_block_filter(),
+        "getVersion" => Box::new(get_version),
         _ => unreachable!(),
     }
 }
 
-macro_rules! send_request {
-    ($web3:ident, $param:ident) => {{
-        let now = Instant::now();
-        let result = $web3.api::<Cita<Http>>().call($param).wait();
-        let dur = now.elapsed();
-        let nums = match result {
-            Ok(_) => (1, 0, 0),
-            Err(err) => match *err.kind() {
-                Web3ErrorKind::Rpc(_)
-                | Web3ErrorKind::InvalidResponse(_)
-                | Web3ErrorKind::Decoder(_) => (0, 1, 0),
-                _ => (0, 1, 0),
-            },
-        };
-        (dur, nums)
-    }};
+pub fn fetch_height(web3: &Web3Client) -> Result<u64, Web3Error> {
+    let param = types::request::BlockNumberParams::new();
+    let height: U256 = web3.api::<Cita<AnyTransport>>().call(param).wait()?.into();
+    Ok(height.low_u64())
 }
 
-fn peer_count(web3: &Web3Http) -> RespStat {
-    let param = types::request::PeerCountParams::new();
-    send_request!(web3, param)
+#[derive(Debug)]
+pub struct BlockInfo {
+    pub height: u64,
+    pub hash: H256,
+    // Milliseconds since the UNIX epoch.
+    pub timestamp: u64,
+    pub tx_hashes: Vec<H256>,
 }
 
-fn block_number(web3: &Web3Http) -> RespStat {
-    let param = types::request::BlockNumberParams::new();
-    send_request!(web3, param)
+pub fn fetch_block(web3: &Web3Client, height: u64) -> Result<BlockInfo, Web3Error> {
+    use types::rpctypes::{BlockNumber, BlockTransaction, Boolean};
+    let param = types::request::GetBlockByNumberParams::new(
+        BlockNumber::new(height.into()),
+        Boolean::new(false),
+    );
+    let block = web3.api::<Cita<AnyTransport>>().call(param).wait()?;
+    let tx_hashes = block
+        .body
+        .transactions
+        .into_iter()
+        .map(|tx| match tx {
+            BlockTransaction::Hash(hash) => hash,
+            BlockTransaction::Full(tx) => tx.hash,
+        })
+        .collect();
+    Ok(BlockInfo {
+        height,
+        hash: block.hash,
+        timestamp: block.header.timestamp,
+        tx_hashes,
+    })
 }
 
-fn get_meta_data(web3: &Web3Http) -> RespStat {
+pub fn fetch_balance(web3: &Web3Client, address: Address) -> Result<U256, Web3Error> {
     let block_number = types::rpctypes::BlockNumber::latest();
-    let param = types::request::GetMetaDataParams::new(block_number);
-    send_request!(web3, param)
+    let param = types::request::GetBalanceParams::new(address.into(), block_number);
+    let balance: U256 = web3.api::<Cita<AnyTransport>>().call(param).wait()?.into();
+    Ok(balance)
 }
 
-fn gen_send_raw_transaction<'a>() -> Box<Fn(&'a Web3Http) -> RespStat> {
-    use cita_crypto::{CreateKey, KeyPair, PrivKey};
-    use cita_types::{H256, U256};
-    use libproto::{blockchain::Transaction, TryInto};
-    use std::str::FromStr;
-    use std::thread;
+/// Fetches the addresses of the contract created and the contracts logged by a transaction.
+pub fn fetch_contract_addresses(web3: &Web3Client, hash: H256) -> Result<Vec<Address>, Web3Error> {
+    let param = types::request::GetTransactionReceiptParams::new(hash.into());
+    let receipt = web3.api::<Cita<AnyTransport>>().call(param).wait()?;
+    let addresses = receipt
+        .map(|receipt| {
+            receipt
+                .contract_address
+                .into_iter()
+                .chain(receipt.logs.into_iter().map(|log| log.address))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(addresses)
+}
+
+/// Fetches the quota which is used by a committed transaction.
+pub fn fetch_quota_used(web3: &Web3Client, hash: H256) -> Result<U256, Web3Error> {
+    let param = types::request::GetTransactionReceiptParams::new(hash.into());
+    let receipt = web3
+        .transport()
+        .execute(param.method_name(), param.value_vec())
+        .wait()?;
+    receipt
+        .get("quotaUsed")
+        .or_else(|| receipt.get("gasUsed"))
+        .and_then(Value::as_str)
+        .and_then(|quota| u64::from_str_radix(quota.trim_start_matches("0x"), 16).ok())
+        .map(U256::from)
+        .ok_or_else(|| {
+            let msg = format!("no quota used in the receipt of {:?}", hash);
+            Web3ErrorKind::InvalidResponse(msg).into()
+        })
+}
 
-    fn import_keypair(keystr: &str) -> KeyPair {
-        if &keystr[0..2] != "0x" {
-            panic!("Please use 0x-prefix for private key.")
+/// Fetches the price of the quota, it is zero unless the chain charges for the quota.
+pub fn fetch_quota_price(web3: &Web3Client) -> Result<U256, Web3Error> {
+    use types::rpctypes::{BlockNumber, CallRequest};
+
+    let param = types::request::GetMetaDataParams::new(BlockNumber::latest());
+    let metadata = web3
+        .transport()
+        .execute(param.method_name(), param.value_vec())
+        .wait()?;
+    // 0 is the quota model, 1 is the charge model.
+    let model = metadata.get("economicalModel").and_then(Value::as_u64);
+    if model.unwrap_or(0) == 0 {
+        return Ok(U256::zero());
+    }
+    let data = encode_call("getQuotaPrice()", &[]).unwrap();
+    let request = CallRequest::new(None, PRICE_MANAGER.into(), Some(data.into()));
+    let param = types::request::CallParams::new(request, BlockNumber::latest());
+    let price = web3
+        .transport()
+        .execute(param.method_name(), param.value_vec())
+        .wait()?;
+    price
+        .as_str()
+        .and_then(|price| price.trim_start_matches("0x").from_hex::<Vec<u8>>().ok())
+        .map(|bytes| U256::from(&bytes[..]))
+        .ok_or_else(|| {
+            let msg = format!("invalid quota price: {}", price);
+            Web3ErrorKind::InvalidResponse(msg).into()
+        })
+}
+
+/// Checks the result of a call, returns the hash if a transaction is sent.
+pub type Checked = Result<Option<H256>, Failure>;
+
+/// A JSON-RPC call which is ready to send, and how to check its result.
+pub struct Prepared {
+    // The method, unless another category is set.
+    category: &'static str,
+    method: &'static str,
+    params: Vec<Value>,
+    // Checks the result with the time when it was sent.
+    check: Box<Fn(Value, Instant) -> Checked>,
+    // It is not sent if it has failed before, such as to fetch the data to build it.
+    failure: Option<Failure>,
+}
+
+impl Prepared {
+    /// Checks the decoded response, `|_, _| Ok(())` if any response is fine.
+    pub fn new<T, F>(param: T, check: F) -> Self
+    where
+        T: JsonRpcRequest,
+        F: Fn(T::Response, Instant) -> Outcome + 'static,
+    {
+        Self::with_hash(param, move |resp, sent_at| {
+            check(resp, sent_at).map(|()| None)
+        })
+    }
+
+    /// Checks the decoded response, and returns the hash of the sent transaction.
+    pub fn with_hash<T, F>(param: T, check: F) -> Self
+    where
+        T: JsonRpcRequest,
+        F: Fn(T::Response, Instant) -> Checked + 'static,
+    {
+        let method = param.method_name();
+        let params = param.value_vec();
+        let check = move |value, sent_at| {
+            serde_json::from_value::<T::Response>(value)
+                .map_err(|err| Failure::new(FailureKind::Decode, err.to_string()))
+                .and_then(|resp| check(resp, sent_at))
+        };
+        Self {
+            category: method,
+            method,
+            params,
+            check: Box::new(check),
+            failure: None,
         }
-        let privkey = PrivKey::from_str(&keystr[2..])
-            .map_err(|err| panic!("failed to parse private key {}: {}", keystr, err))
-            .unwrap();
-        KeyPair::from_privkey(privkey)
-            .map_err(|err| panic!("failed to load private key to keypair: {}", err))
-            .unwrap()
     }
 
-    fn generate_nonce() -> String {
-        use rand::distributions::Alphanumeric;
-        use rand::{thread_rng, Rng};
-        let mut rng = thread_rng();
-        rng.sample_iter(&Alphanumeric).take(12).collect()
+    /// A call which has failed before it is sent, it is reported as the category.
+    pub fn failed(category: &'static str, failure: Failure) -> Self {
+        let unsent = failure.clone();
+        Self {
+            category,
+            method: category,
+            params: Vec::new(),
+            check: Box::new(move |_, _| Err(unsent.clone())),
+            failure: Some(failure),
+        }
     }
 
-    fn fetch_chain_id(web3: &Web3Http) -> U256 {
-        let block_number = types::rpctypes::BlockNumber::latest();
-        let param = types::request::GetMetaDataParams::new(block_number);
-        let metadata = web3.api::<Cita<Http>>().call(param).wait().unwrap();
-        metadata.chain_id_v1.into()
+    /// Reports it as the category instead of the method.
+    pub fn of(mut self, category: &'static str) -> Self {
+        self.category = category;
+        self
     }
+}
+
+macro_rules! prepare_request {
+    ($param:ident) => {
+        prepare_request!($param, |_, _| Ok(()))
+    };
+    ($param:ident, $check:expr) => {
+        Prepared::new($param, $check)
+    };
+}
 
-    fn fetch_height(web3: &Web3Http) -> u64 {
-        let param = types::request::BlockNumberParams::new();
-        let height: U256 = web3.api::<Cita<Http>>().call(param).wait().unwrap().into();
-        height.low_u64()
+/// Sends a call alone.
+pub fn send(web3: &Web3Client, prepared: Prepared) -> StepOutcome {
+    if let Some(failure) = prepared.failure {
+        return StepOutcome::new(prepared.category, Duration::new(0, 0), Err(failure));
     }
+    let sent_at = Instant::now();
+    let result = web3
+        .transport()
+        .execute(prepared.method, prepared.params)
+        .wait();
+    let dur = sent_at.elapsed();
+    let checked = result
+        .map_err(Failure::from)
+        .and_then(|value| (prepared.check)(value, sent_at));
+    StepOutcome::new(prepared.category, dur, checked)
+}
 
-    let chain_id = Arc::new(RwLock::new(None));
-    let height = Arc::new(RwLock::new(0u64));
-    let height_reset = height.clone();
+/// Sends calls in one batch, all calls share the latency of the batch.
+pub fn send_batch(web3: &Web3Client, prepared: Vec<Prepared>) -> (Duration, Vec<StepOutcome>) {
+    let (prepared, mut failed) = split_failed(prepared);
+    if prepared.is_empty() {
+        return (Duration::new(0, 0), failed);
+    }
+    let transport = web3.transport();
+    let mut requests = Vec::with_capacity(prepared.len());
+    let mut checks = Vec::with_capacity(prepared.len());
+    for call in prepared {
+        requests.push(transport.prepare(call.method, call.params));
+        checks.push((call.category, call.check));
+    }
+    let sent_at = Instant::now();
+    let result = transport.send_batch(requests).wait();
+    let dur = sent_at.elapsed();
+    let mut steps = check_batch(result, &checks, dur, sent_at);
+    steps.append(&mut failed);
+    (dur, steps)
+}
 
-    let wait_secs = Duration::from_secs(120);
-    thread::spawn(move || loop {
-        thread::sleep(wait_secs);
-        *height_reset.write().unwrap() = 0;
-    });
+// Splits the calls which have failed before they are sent, they are never put into a batch.
+fn split_failed(prepared: Vec<Prepared>) -> (Vec<Prepared>, Vec<StepOutcome>) {
+    let mut rest = Vec::with_capacity(prepared.len());
+    let mut failed = Vec::new();
+    for call in prepared {
+        match call.failure {
+            Some(failure) => failed.push(StepOutcome::new(
+                call.category,
+                Duration::new(0, 0),
+                Err(failure),
+            )),
+            None => rest.push(call),
+        }
+    }
+    (rest, failed)
+}
 
-    let keystr = "0x1000000000000000000000000000000000000000000000000000000000000000";
-    let keypair = import_keypair(&keystr);
-
-    let closure = move |web3| {
-        let chain_id = {
-            let id = { *chain_id.read().unwrap() };
-            id.unwrap_or_else(|| {
-                let id_new = fetch_chain_id(web3);
-                {
-                    *chain_id.write().unwrap() = Some(id_new);
-                }
-                id_new
+fn check_batch(
+    result: Result<Vec<Result<Value, Web3Error>>, Web3Error>,
+    checks: &[(&'static str, Box<Fn(Value, Instant) -> Checked>)],
+    dur: Duration,
+    sent_at: Instant,
+) -> Vec<StepOutcome> {
+    match result {
+        Ok(values) => values
+            .into_iter()
+            .zip(checks.iter())
+            .map(|(value, (category, check))| {
+                let checked = value.map_err(Failure::from).and_then(|v| check(v, sent_at));
+                StepOutcome::new(*category, dur, checked)
             })
+            .collect(),
+        Err(err) => {
+            let failure = Failure::from(err);
+            checks
+                .iter()
+                .map(|(category, _)| StepOutcome::new(*category, dur, Err(failure.clone())))
+                .collect()
+        }
+    }
+}
+
+/// Sends a call without waiting, the latency is measured from `since`.
+pub fn send_async(
+    web3: &Web3Client,
+    prepared: Prepared,
+    since: Instant,
+) -> Box<Future<Item = StepOutcome, Error = ()>> {
+    let category = prepared.category;
+    if let Some(failure) = prepared.failure {
+        let step = StepOutcome::new(category, since.elapsed(), Err(failure));
+        return Box::new(future::ok(step));
+    }
+    let check = prepared.check;
+    let sent_at = Instant::now();
+    let future = web3
+        .transport()
+        .execute(prepared.method, prepared.params)
+        .then(move |result| {
+            let dur = since.elapsed();
+            let checked = result
+                .map_err(Failure::from)
+                .and_then(|value| check(value, sent_at));
+            Ok::<_, ()>(StepOutcome::new(category, dur, checked))
+        });
+    Box::new(future)
+}
+
+/// Sends calls in one batch without waiting, the latency is measured from `since`.
+pub fn send_batch_async(
+    web3: &Web3Client,
+    prepared: Vec<Prepared>,
+    since: Instant,
+) -> Box<Future<Item = (Duration, Vec<StepOutcome>), Error = ()>> {
+    let (prepared, mut failed) = split_failed(prepared);
+    if prepared.is_empty() {
+        return Box::new(future::ok((since.elapsed(), failed)));
+    }
+    let transport = web3.transport();
+    let mut requests = Vec::with_capacity(prepared.len());
+    let mut checks = Vec::with_capacity(prepared.len());
+    for call in prepared {
+        requests.push(transport.prepare(call.method, call.params));
+        checks.push((call.category, call.check));
+    }
+    let sent_at = Instant::now();
+    let future = transport.send_batch(requests).then(move |result| {
+        let dur = since.elapsed();
+        let mut steps = check_batch(result, &checks, dur, sent_at);
+        steps.append(&mut failed);
+        Ok::<_, ()>((dur, steps))
+    });
+    Box::new(future)
+}
+
+fn peer_count(_: &Web3Client) -> Prepared {
+    let param = types::request::PeerCountParams::new();
+    prepare_request!(param)
+}
+
+fn block_number(_: &Web3Client) -> Prepared {
+    let param = types::request::BlockNumberParams::new();
+    prepare_request!(param)
+}
+
+fn get_meta_data(_: &Web3Client) -> Prepared {
+    let block_number = types::rpctypes::BlockNumber::latest();
+    let param = types::request::GetMetaDataParams::new(block_number);
+    prepare_request!(param)
+}
+
+fn get_version(_: &Web3Client) -> Prepared {
+    let param = types::request::GetVersionParams::new();
+    prepare_request!(param)
+}
+
+fn gen_sample_query(category: &str, sample: Arc<ChainSample>) -> Box<Fn(&Web3Client) -> Prepared> {
+    use types::request::*;
+    use types::rpctypes::{BlockNumber, Boolean, Filter};
+
+    fn at(height: u64) -> BlockNumber {
+        BlockNumber::new(height.into())
+    }
+
+    match category {
+        "getBlockByNumber" => Box::new(move |_: &Web3Client| {
+            let param = GetBlockByNumberParams::new(at(sample.height()), Boolean::new(false));
+            prepare_request!(param)
+        }),
+        "getBlockByHash" => Box::new(move |_: &Web3Client| {
+            let hash = sample.block_hash();
+            let param = GetBlockByHashParams::new(hash.into(), Boolean::new(false));
+            prepare_request!(param)
+        }),
+        "getTransaction" => Box::new(move |_: &Web3Client| {
+            let param = GetTransactionParams::new(sample.tx_hash().into());
+            prepare_request!(param)
+        }),
+        "getTransactionReceipt" => Box::new(move |_: &Web3Client| {
+            let param = GetTransactionReceiptParams::new(sample.tx_hash().into());
+            prepare_request!(param)
+        }),
+        "getTransactionCount" => Box::new(move |_: &Web3Client| {
+            let param =
+                GetTransactionCountParams::new(sample.address().into(), at(sample.height()));
+            prepare_request!(param)
+        }),
+        "getBalance" => Box::new(move |_: &Web3Client| {
+            let param = GetBalanceParams::new(sample.address().into(), at(sample.height()));
+            prepare_request!(param)
+        }),
+        "getCode" => Box::new(move |_: &Web3Client| {
+            let param = GetCodeParams::new(sample.contract().into(), BlockNumber::latest());
+            prepare_request!(param)
+        }),
+        "getAbi" => Box::new(move |_: &Web3Client| {
+            let param = GetAbiParams::new(sample.contract().into(), BlockNumber::latest());
+            prepare_request!(param)
+        }),
+        "getLogs" => Box::new(move |_: &Web3Client| {
+            let height = sample.height();
+            let filter = Filter::new(at(height), at(height), None, None);
+            let param = GetLogsParams::new(filter);
+            prepare_request!(param)
+        }),
+        "getTransactionProof" => Box::new(move |_: &Web3Client| {
+            let param = GetTransactionProofParams::new(sample.tx_hash().into());
+            prepare_request!(param)
+        }),
+        "getBlockHeader" => Box::new(move |_: &Web3Client| {
+            let param = GetBlockHeaderParams::new(at(sample.height()));
+            prepare_request!(param)
+        }),
+        "getStateProof" => Box::new(move |_: &Web3Client| {
+            // The first slot of the storage.
+            let position = H256::zero();
+            let param = GetStateProofParams::new(
+                sample.contract().into(),
+                position.into(),
+                BlockNumber::latest(),
+            );
+            prepare_request!(param)
+        }),
+        _ => unreachable!(),
+    }
+}
+
+/// Polls the changes of a block filter, the first steps install it until one succeeds.
+///
+/// The polled blocks are not delivered to the observer, since the latency would be the
+/// interval of polling. Subscribe to them over WebSocket to measure the delivery.
+fn gen_block_filter() -> Box<Fn(&Web3Client) -> Prepared> {
+    let filter_id = Arc::new(RwLock::new(None));
+    let closure = move |_: &Web3Client| {
+        let id = { *filter_id.read().unwrap() };
+        if let Some(id) = id {
+            let param = types::request::GetFilterChangesParams::new(id.into());
+            return prepare_request!(param);
+        }
+        let installed = filter_id.clone();
+        let param = types::request::NewBlockFilterParams::new();
+        prepare_request!(param, move |id, _| {
+            let id: U256 = id.into();
+            *installed.write().unwrap() = Some(id);
+            Ok(())
+        })
+    };
+    Box::new(closure)
+}
+
+/// Subscribes to new blocks over WebSocket, then receives the pushed blocks one by one.
+///
+/// The first step is the subscription, each later step waits for the next block, and the
+/// block is delivered to the observer when it is received.
+pub struct BlockSubscription {
+    observer: Arc<Observer>,
+    // Only after subscribed, the notifications are forwarded by another thread.
+    notifications: Option<Receiver<Result<Value, Failure>>>,
+}
+
+impl BlockSubscription {
+    pub fn new(observer: Arc<Observer>) -> Self {
+        Self {
+            observer,
+            notifications: None,
+        }
+    }
+
+    pub fn step(&mut self, web3: &Web3Client) -> StepOutcome {
+        let started_at = Instant::now();
+        let checked = if self.notifications.is_some() {
+            self.receive()
+        } else {
+            self.subscribe(web3)
         };
-        let height = {
-            let h = { *height.read().unwrap() };
-            if h == 0 {
-                let h_new = fetch_height(web3);
-                {
-                    *height.write().unwrap() = h_new;
-                }
-                h_new
-            } else {
-                h
+        StepOutcome::new(
+            "newBlockFilter",
+            started_at.elapsed(),
+            checked.map(|()| None),
+        )
+    }
+
+    fn subscribe(&mut self, web3: &Web3Client) -> Outcome {
+        let params = vec![Value::from(NEW_BLOCK_EVENT)];
+        let id = web3
+            .transport()
+            .execute(SUBSCRIBE_METHOD, params)
+            .wait()
+            .map_err(Failure::from)?;
+        let id = id.as_str().map(str::to_owned).ok_or_else(|| {
+            Failure::new(FailureKind::Decode, format!("invalid subscription: {}", id))
+        })?;
+        let notifications = web3.transport().subscribe(id).ok_or_else(|| {
+            let msg = "only WebSocket pushes the notifications".to_owned();
+            Failure::new(FailureKind::Other, msg)
+        })?;
+        let (sender, receiver) = channel();
+        // Stops when the connection is closed, or at the first block after the soldier stops.
+        thread::spawn(move || {
+            let _ = notifications
+                .then(move |result| sender.send(result.map_err(Failure::from)))
+                .for_each(|()| Ok(()))
+                .wait();
+        });
+        self.notifications = Some(receiver);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Outcome {
+        let timeout = Duration::from_secs(NOTIFICATION_TIMEOUT_SECS);
+        let result = self.notifications.as_ref().unwrap().recv_timeout(timeout);
+        let value = match result {
+            Ok(value) => value?,
+            Err(RecvTimeoutError::Timeout) => {
+                let msg = format!("no block is pushed in {}s", NOTIFICATION_TIMEOUT_SECS);
+                return Err(Failure::new(FailureKind::Timeout, msg));
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                // Subscribe again at the next step.
+                self.notifications = None;
+                let msg = "the subscription is closed".to_owned();
+                return Err(Failure::new(FailureKind::Transport, msg));
             }
         };
-        let nonce = generate_nonce();
-        let utx = {
-            let mut tx = Transaction::new();
-            tx.set_chain_id_v1(H256::from(chain_id).to_vec());
-            tx.set_valid_until_block(height + 100);
-            tx.set_nonce(nonce);
-            tx.set_quota(1_000_000);
-            tx.set_version(1);
-            tx.set_data(Vec::new());
-            tx.set_value(vec![0u8; 32]);
-            tx.sign(*keypair.privkey()).take_transaction_with_sig()
+        let received = {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            now.as_secs() * 1000 + u64::from(now.subsec_millis())
         };
+        let hash = value["hash"]
+            .as_str()
+            .and_then(|hash| hash.trim_start_matches("0x").parse::<H256>().ok())
+            .ok_or_else(|| {
+                Failure::new(FailureKind::Decode, format!("invalid block: {}", value))
+            })?;
+        self.observer.deliver(hash, received);
+        Ok(())
+    }
+}
+
+fn gen_call(tx_params: TxParams) -> Box<Fn(&Web3Client) -> Prepared> {
+    use types::rpctypes::{BlockNumber, CallRequest};
+    let to = tx_params.to.expect("the contract to call is required");
+    let data = tx_params.data;
+    let closure = move |_: &Web3Client| {
+        let request = CallRequest::new(None, to, Some(data.clone().into()));
+        let param = types::request::CallParams::new(request, BlockNumber::latest());
+        prepare_request!(param)
+    };
+    Box::new(closure)
+}
+
+/// A transaction which is signed in advance.
+#[derive(Debug, Clone)]
+pub struct SignedTx {
+    pub valid_until_block: u64,
+    pub bytes: Vec<u8>,
+}
+
+pub fn fetch_chain_id(web3: &Web3Client) -> Result<U256, Web3Error> {
+    let block_number = types::rpctypes::BlockNumber::latest();
+    let param = types::request::GetMetaDataParams::new(block_number);
+    let metadata = web3.api::<Cita<AnyTransport>>().call(param).wait()?;
+    Ok(metadata.chain_id_v1.into())
+}
+
+/// Builds a transaction and signs it, returns the transfer in it if any.
+pub fn sign_transaction(
+    key: &PrivateKey,
+    chain_id: U256,
+    valid_until_block: u64,
+    tx_params: &TxParams,
+) -> (SignedTx, Option<Transfer>) {
+    use libproto::{blockchain::Transaction, TryInto};
+    use rand::distributions::Alphanumeric;
+    use rand::seq::SliceRandom;
+    use rand::{thread_rng, Rng};
 
-        let tx_bytes: Vec<u8> = utx.try_into().unwrap();
-        let param = types::request::SendRawTransactionParams::new(tx_bytes.into());
-        send_request!(web3, param)
+    let mut rng = thread_rng();
+    let nonce: String = rng.sample_iter(&Alphanumeric).take(12).collect();
+    let transfer = if tx_params.receivers.is_empty() {
+        None
+    } else {
+        let sender = key.address();
+        let receivers = tx_params
+            .receivers
+            .iter()
+            .filter(|address| **address != sender)
+            .collect::<Vec<_>>();
+        // The config requires at least 2 distinct addresses.
+        let to = **receivers
+            .choose(&mut rng)
+            .expect("no receiver other than the sender");
+        Some(Transfer {
+            from: sender,
+            to,
+            value: tx_params.value,
+        })
+    };
+    let utx = {
+        let mut tx = Transaction::new();
+        tx.set_chain_id_v1(H256::from(chain_id).to_vec());
+        tx.set_valid_until_block(valid_until_block);
+        tx.set_nonce(nonce);
+        tx.set_quota(tx_params.quota);
+        tx.set_version(1);
+        if let Some(ref transfer) = transfer {
+            tx.set_to_v1(transfer.to.to_vec());
+        } else if let Some(to) = tx_params.to {
+            tx.set_to_v1(to.to_vec());
+        }
+        tx.set_data(tx_params.data.clone());
+        tx.set_value(H256::from(tx_params.value).to_vec());
+        tx.sign(*key.privkey()).take_transaction_with_sig()
+    };
+    let bytes: Vec<u8> = utx.try_into().unwrap();
+    let signed = SignedTx {
+        valid_until_block,
+        bytes,
+    };
+    (signed, transfer)
+}
+
+fn prepare_signed_transaction(
+    node: &Node,
+    observer: &Arc<Observer>,
+    tx: &SignedTx,
+    transfer: Option<Transfer>,
+) -> Prepared {
+    let param = types::request::SendRawTransactionParams::new(tx.bytes.clone().into());
+    let node = node.clone();
+    let observer = observer.clone();
+    let valid_until_block = tx.valid_until_block;
+    Prepared::with_hash(param, move |resp: types::rpctypes::TxResponse, sent_at| {
+        if resp.status == "OK" {
+            if observer.is_tracking() {
+                observer.track(
+                    &node,
+                    resp.hash,
+                    sent_at,
+                    valid_until_block,
+                    transfer.clone(),
+                );
+            }
+            Ok(Some(resp.hash))
+        } else {
+            Err(Failure::new(FailureKind::Rejected, resp.status))
+        }
+    })
+}
+
+/// Sends the transactions which are signed in advance.
+pub fn gen_send_signed_transaction(
+    node: &Node,
+    observer: Arc<Observer>,
+) -> Box<Fn(&ReplayTx) -> Prepared> {
+    let node = node.clone();
+    let closure = move |replayed: &ReplayTx| {
+        let transfer = replayed.transfer.clone();
+        prepare_signed_transaction(&node, &observer, &replayed.tx, transfer).of(replayed.category)
+    };
+    Box::new(closure)
+}
+
+fn gen_send_raw_transaction(
+    node: &Node,
+    observer: Arc<Observer>,
+    key: PrivateKey,
+    tx_params: TxParams,
+    chain_id: U256,
+) -> Box<Fn(&Web3Client) -> Prepared> {
+    let node = node.clone();
+    // The height is polled by the observer, so no call is waited for here.
+    let closure = move |_: &Web3Client| {
+        let height = match observer.latest_height() {
+            Some(height) => height,
+            None => {
+                let msg = "the height of the chain is not fetched yet".to_owned();
+                return Prepared::failed(
+                    "sendRawTransaction",
+                    Failure::new(FailureKind::Other, msg),
+                );
+            }
+        };
+        let (tx, transfer) = sign_transaction(&key, chain_id, height + 100, &tx_params);
+        prepare_signed_transaction(&node, &observer, &tx, transfer)
     };
     Box::new(closure)
 }
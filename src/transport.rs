@@ -0,0 +1,197 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::sync::Arc;
+
+use cita_web3::web3::{
+    api::SubscriptionId,
+    futures::{future::Either, Stream},
+    rpc,
+    transports::{EventLoopHandle, Http, WebSocket},
+    BatchTransport, DuplexTransport, Error as Web3Error, RequestId, Transport, Web3,
+};
+use rustls::ClientConfig;
+use tokio_core::reactor::Handle;
+
+use crate::config::Node;
+use crate::tls::{TlsOptions, TlsTransport};
+
+pub const PROTOCOLS: &[&str] = &["http", "https", "ws", "wss"];
+
+pub type Web3Client = Web3<AnyTransport>;
+
+/// The pushed notifications of a subscription.
+pub type Notifications = Box<Stream<Item = rpc::Value, Error = Web3Error> + Send>;
+
+/// Sends requests by HTTP, through a persistent WebSocket connection, or over TLS with
+/// the TLS options.
+#[derive(Debug, Clone)]
+pub enum AnyTransport {
+    Http(Http),
+    WebSocket(WebSocket),
+    Tls(TlsTransport),
+}
+
+impl AnyTransport {
+    /// Receives the notifications of the subscription, only WebSocket pushes them.
+    pub fn subscribe(&self, id: String) -> Option<Notifications> {
+        match *self {
+            AnyTransport::WebSocket(ref transport) => {
+                Some(Box::new(transport.subscribe(&SubscriptionId::from(id))))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Transport for AnyTransport {
+    type Out = Either<
+        Either<<Http as Transport>::Out, <WebSocket as Transport>::Out>,
+        <TlsTransport as Transport>::Out,
+    >;
+
+    fn prepare(&self, method: &str, params: Vec<rpc::Value>) -> (RequestId, rpc::Call) {
+        match *self {
+            AnyTransport::Http(ref transport) => transport.prepare(method, params),
+            AnyTransport::WebSocket(ref transport) => transport.prepare(method, params),
+            AnyTransport::Tls(ref transport) => transport.prepare(method, params),
+        }
+    }
+
+    fn send(&self, id: RequestId, request: rpc::Call) -> Self::Out {
+        match *self {
+            AnyTransport::Http(ref transport) => Either::A(Either::A(transport.send(id, request))),
+            AnyTransport::WebSocket(ref transport) => {
+                Either::A(Either::B(transport.send(id, request)))
+            }
+            AnyTransport::Tls(ref transport) => Either::B(transport.send(id, request)),
+        }
+    }
+}
+
+impl BatchTransport for AnyTransport {
+    type Batch = Either<
+        Either<<Http as BatchTransport>::Batch, <WebSocket as BatchTransport>::Batch>,
+        <TlsTransport as BatchTransport>::Batch,
+    >;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, rpc::Call)>,
+    {
+        match *self {
+            AnyTransport::Http(ref transport) => {
+                Either::A(Either::A(transport.send_batch(requests)))
+            }
+            AnyTransport::WebSocket(ref transport) => {
+                Either::A(Either::B(transport.send_batch(requests)))
+            }
+            AnyTransport::Tls(ref transport) => Either::B(transport.send_batch(requests)),
+        }
+    }
+}
+
+/// Knows how to connect to the nodes.
+#[derive(Clone)]
+pub struct Connector {
+    protocol: String,
+    // Only for https or wss with the TLS options.
+    tls: Option<(Arc<ClientConfig>, TlsOptions)>,
+}
+
+impl fmt::Debug for Connector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Connector({}, tls: {})",
+            self.protocol,
+            self.tls.is_some()
+        )
+    }
+}
+
+impl Connector {
+    pub fn new(protocol: &str, tls: Option<&TlsOptions>) -> Result<Self, String> {
+        let tls = if let Some(options) = tls {
+            if protocol != "https" && protocol != "wss" {
+                return Err(format!(
+                    "the TLS options are only for https and wss, but the protocol is {}",
+                    protocol
+                ));
+            }
+            Some((Arc::new(options.client_config()?), options.clone()))
+        } else {
+            None
+        };
+        Ok(Self {
+            protocol: protocol.to_owned(),
+            tls,
+        })
+    }
+
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    /// Whether the node can push notifications through the connection, the notifications by
+    /// wss with the TLS options are not received.
+    pub fn is_duplex(&self) -> bool {
+        (self.protocol == "ws" || self.protocol == "wss") && self.tls.is_none()
+    }
+
+    /// Connects to the nodes on the event loop of the handle, the requests are sent when the
+    /// event loop runs. Each HTTP connection sends up to `max_parallel` requests at the same time.
+    pub fn connect_all(
+        &self,
+        node: &[Node],
+        max_parallel: usize,
+        handle: &Handle,
+    ) -> Vec<Web3Client> {
+        let websocket = self.protocol == "ws" || self.protocol == "wss";
+        let transports = node
+            .iter()
+            .map(|n| {
+                let url = format!("{}://{}:{}", self.protocol, n.host, n.port);
+                if let Some((ref config, ref options)) = self.tls {
+                    TlsTransport::with_event_loop(config.clone(), options, n, websocket, handle)
+                        .map(AnyTransport::Tls)
+                } else if websocket {
+                    WebSocket::with_event_loop(&url, handle).map(AnyTransport::WebSocket)
+                } else {
+                    Http::with_event_loop(&url, handle, max_parallel).map(AnyTransport::Http)
+                }
+            })
+            .collect::<Result<Vec<_>, Web3Error>>()
+            .map_err(|err| panic!("failed to connect to {:?}: {}", node, err))
+            .unwrap();
+        transports.into_iter().map(Web3::new).collect()
+    }
+
+    /// Connects to a node, the connection is closed when the handle is dropped.
+    pub fn connect(&self, node: &Node) -> (EventLoopHandle, Web3Client) {
+        let url = format!("{}://{}:{}", self.protocol, node.host, node.port);
+        let result = if let Some((ref config, ref options)) = self.tls {
+            let (config, options, node) = (config.clone(), options.clone(), node.clone());
+            let websocket = self.protocol == "wss";
+            EventLoopHandle::spawn(move |handle| {
+                TlsTransport::with_event_loop(config, &options, &node, websocket, handle)
+                    .map(AnyTransport::Tls)
+            })
+        } else if self.protocol == "ws" || self.protocol == "wss" {
+            WebSocket::new(&url)
+                .map(|(eloop, transport)| (eloop, AnyTransport::WebSocket(transport)))
+        } else {
+            Http::new(&url).map(|(eloop, transport)| (eloop, AnyTransport::Http(transport)))
+        };
+        let (eloop, transport) = result
+            .map_err(|err: Web3Error| panic!("failed to connect to {}: {}", url, err))
+            .unwrap();
+        (eloop, Web3::new(transport))
+    }
+}
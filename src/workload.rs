@@ -0,0 +1,279 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cita_types::{Address, H256, U256};
+use rand::distributions::WeightedIndex;
+use rand::rngs::ThreadRng;
+use rand::{thread_rng, Rng};
+
+use crate::account::PrivateKey;
+use crate::config::{AppConfig, Node};
+use crate::observer::Observer;
+use crate::permission::grant_permissions;
+use crate::replay::{Replay, ReplayTx};
+use crate::report::{FailureKind, Outcome};
+use crate::sample::ChainSample;
+use crate::transaction::{
+    fetch_chain_id, gen_send_signed_transaction, get_func_core, is_sample_category, is_tx_category,
+    send, BlockSubscription, Checked, Prepared, TxParams, JSONRPC_METHODS,
+};
+use crate::transport::Web3Client;
+
+/// What a step has done, after its response is checked.
+#[derive(Debug, Clone)]
+pub struct StepOutcome {
+    pub category: &'static str,
+    // Includes the lag behind the intended time when a rate is set.
+    pub latency: Duration,
+    pub outcome: Outcome,
+    // Only when a transaction is accepted.
+    pub tx_hash: Option<H256>,
+}
+
+impl StepOutcome {
+    pub fn new(category: &'static str, latency: Duration, checked: Checked) -> Self {
+        let (outcome, tx_hash) = match checked {
+            Ok(tx_hash) => (Ok(()), tx_hash),
+            Err(failure) => (Err(failure), None),
+        };
+        Self {
+            category,
+            latency,
+            outcome,
+            tx_hash,
+        }
+    }
+
+    pub fn error_kind(&self) -> Option<FailureKind> {
+        self.outcome.as_ref().err().map(|failure| failure.kind)
+    }
+}
+
+/// The state of a workload in a soldier.
+pub trait Soldier {
+    /// Builds the call of the next step, the soldier stops when `None` is returned.
+    ///
+    /// The engine sends the call in a batch or without waiting. The async engine calls it on
+    /// the event loop which sends the calls, so it should not wait for any call.
+    fn next(&mut self, web3: &Web3Client) -> Option<Prepared>;
+
+    /// Takes a step and returns its outcome, the soldier stops when `None` is returned.
+    ///
+    /// The sync engine calls it when calls are not batched, it sends the next call by default.
+    fn step(&mut self, web3: &Web3Client) -> Option<StepOutcome> {
+        self.next(web3).map(|call| send(web3, call))
+    }
+
+    /// Called with the outcome of each step.
+    fn done(&mut self, _outcome: &StepOutcome) {}
+}
+
+/// A workload, which is scheduled and reported as the built-in categories.
+pub trait Workload: Send + Sync {
+    /// The categories of the steps, the report is broken down by them if there are more than one.
+    fn categories(&self) -> Vec<&'static str>;
+
+    /// Whether the steps send transactions, the accepted ones are confirmed if it is true.
+    fn sends_transactions(&self) -> bool {
+        false
+    }
+
+    /// Called once before the soldiers start, such as to fetch the chain id or deploy contracts.
+    fn prepare(&mut self, _web3: &Web3Client) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Builds a soldier for the node, the ids of soldiers are counted from 0.
+    fn init<'a>(&'a self, node: &Node, soldier_id: usize) -> Box<Soldier + 'a>;
+
+    /// Called once after all soldiers have finished.
+    fn finish(&self, _web3: &Web3Client) {}
+}
+
+impl fmt::Debug for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Workload({})", self.categories().join(","))
+    }
+}
+
+/// Sends the categories in the config by their weights, or replays the signed transactions.
+pub struct CategoryWorkload {
+    mix: Vec<(&'static str, u32, TxParams)>,
+    // Keys are assigned to soldiers in turn.
+    key: Vec<PrivateKey>,
+    admin_key: Option<PrivateKey>,
+    contract: Option<Address>,
+    replay: Option<Replay>,
+    observer: Arc<Observer>,
+    chain_id: Option<U256>,
+    sample: Option<Arc<ChainSample>>,
+    // Blocks are pushed instead of polled, only for newBlockFilter over WebSocket.
+    subscribe: bool,
+}
+
+impl CategoryWorkload {
+    pub fn new(c: &AppConfig, observer: Arc<Observer>) -> Self {
+        let mix = c
+            .mix
+            .iter()
+            .map(|item| {
+                let category = JSONRPC_METHODS
+                    .iter()
+                    .find(|method| **method == item.category)
+                    .expect("the category is unknown");
+                (*category, item.weight, TxParams::new(category, c))
+            })
+            .collect::<Vec<_>>();
+        let subscribe = c.connector.is_duplex()
+            && mix
+                .iter()
+                .any(|(category, _, _)| *category == "newBlockFilter");
+        Self {
+            mix,
+            key: c.key.clone(),
+            admin_key: c.admin_key.clone(),
+            contract: c.contract,
+            replay: c.replay.as_ref().map(|(_, txs)| Replay::new(txs.clone())),
+            observer,
+            chain_id: None,
+            sample: None,
+            subscribe,
+        }
+    }
+}
+
+impl Workload for CategoryWorkload {
+    fn categories(&self) -> Vec<&'static str> {
+        match self.replay {
+            Some(ref replay) => replay.categories(),
+            None => self.mix.iter().map(|(category, _, _)| *category).collect(),
+        }
+    }
+
+    fn sends_transactions(&self) -> bool {
+        self.replay.is_some()
+            || self
+                .mix
+                .iter()
+                .any(|(category, _, _)| is_tx_category(category))
+    }
+
+    fn prepare(&mut self, web3: &Web3Client) -> Result<(), String> {
+        if self.replay.is_some() {
+            return Ok(());
+        }
+        if self
+            .mix
+            .iter()
+            .any(|(category, _, _)| is_tx_category(category))
+        {
+            let chain_id = fetch_chain_id(web3)
+                .map_err(|err| format!("failed to fetch the chain id: {}", err))?;
+            self.chain_id = Some(chain_id);
+            if let Some(ref admin_key) = self.admin_key {
+                let mut accounts = self.key.iter().map(PrivateKey::address).collect::<Vec<_>>();
+                accounts.sort();
+                accounts.dedup();
+                grant_permissions(web3, admin_key, chain_id, &accounts)
+                    .map_err(|err| format!("failed to grant the permissions: {}", err))?;
+            }
+        }
+        if self
+            .mix
+            .iter()
+            .any(|(category, _, _)| is_sample_category(category))
+        {
+            let addresses = self.key.iter().map(PrivateKey::address).collect();
+            let contracts = self.contract.iter().cloned().collect();
+            let sample = ChainSample::collect(web3, addresses, contracts)
+                .and_then(|sample| {
+                    for (category, _, _) in self.mix.iter() {
+                        sample.check(category)?;
+                    }
+                    Ok(sample)
+                })
+                .map_err(|err| format!("failed to sample the chain: {}", err))?;
+            self.sample = Some(Arc::new(sample));
+        }
+        Ok(())
+    }
+
+    fn init<'a>(&'a self, node: &Node, soldier_id: usize) -> Box<Soldier + 'a> {
+        let key = &self.key[soldier_id % self.key.len()];
+        // Nothing else is sent when replaying.
+        let func_cores = if self.replay.is_some() {
+            Vec::new()
+        } else {
+            self.mix
+                .iter()
+                .map(|(category, _, tx_params)| {
+                    get_func_core(
+                        category,
+                        node,
+                        self.observer.clone(),
+                        key.clone(),
+                        tx_params.clone(),
+                        self.sample.clone(),
+                        self.chain_id,
+                    )
+                })
+                .collect()
+        };
+        let weights = WeightedIndex::new(self.mix.iter().map(|(_, weight, _)| *weight)).unwrap();
+        let subscription = if self.subscribe {
+            Some(BlockSubscription::new(self.observer.clone()))
+        } else {
+            None
+        };
+        Box::new(CategorySoldier {
+            workload: self,
+            func_cores,
+            subscription,
+            send_signed: gen_send_signed_transaction(node, self.observer.clone()),
+            weights,
+            rng: thread_rng(),
+        })
+    }
+}
+
+struct CategorySoldier<'a> {
+    workload: &'a CategoryWorkload,
+    func_cores: Vec<Box<Fn(&Web3Client) -> Prepared>>,
+    // It is the only category when it is set, and its steps are not calls.
+    subscription: Option<BlockSubscription>,
+    send_signed: Box<Fn(&ReplayTx) -> Prepared>,
+    weights: WeightedIndex<u32>,
+    rng: ThreadRng,
+}
+
+impl<'a> Soldier for CategorySoldier<'a> {
+    fn next(&mut self, web3: &Web3Client) -> Option<Prepared> {
+        // Replayed ones are reported as the categories which they are generated for.
+        if let Some(ref replay) = self.workload.replay {
+            return replay.next().map(|tx| (self.send_signed)(tx));
+        }
+        let mix = &self.workload.mix;
+        let picked = if mix.len() > 1 {
+            self.rng.sample(&self.weights)
+        } else {
+            0
+        };
+        Some((self.func_cores[picked])(web3).of(mix[picked].0))
+    }
+
+    fn step(&mut self, web3: &Web3Client) -> Option<StepOutcome> {
+        if let Some(ref mut subscription) = self.subscription {
+            return Some(subscription.step(web3));
+        }
+        self.next(web3).map(|call| send(web3, call))
+    }
+}
@@ -0,0 +1,737 @@
+// Copyright 2019 Boyu Yang<yangby@cryptape.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runs missions against the mock node.
+
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{env, fs, process};
+
+use cita_types::U256;
+use cita_web3::types::request::{BlockNumberParams, SendRawTransactionParams};
+use cita_web3::types::rpctypes::TxResponse;
+use libproto::{
+    blockchain::{SignedTransaction, UnverifiedTransaction},
+    TryFrom,
+};
+
+use cita_bench::account::PrivateKey;
+use cita_bench::config::{build_commandline, MockConfig, Node};
+use cita_bench::mission::Schedule;
+use cita_bench::report::GeneralSummary;
+use cita_bench::transaction::{fetch_chain_id, fetch_height, sign_transaction, Prepared, TxParams};
+use cita_bench::transport::Web3Client;
+use cita_bench::{
+    carry_out, generate_mission, generate_report, generate_workload_mission, mock, replay,
+    AppConfig, Soldier, StepOutcome, Workload,
+};
+
+const AMOUNT: usize = 20;
+const THREAD: usize = 2;
+const CONTRACT: &str = "0xffffffffffffffffffffffffffffffffff020004";
+const ADMIN_KEY: &str = "0x2000000000000000000000000000000000000000000000000000000000000000";
+
+fn mock_config(error_rate: f64) -> MockConfig {
+    MockConfig {
+        listen: "127.0.0.1:0".to_owned(),
+        chain_id: 1,
+        peer_count: 3,
+        latency: Duration::from_millis(1),
+        error_rate,
+        error_methods: Vec::new(),
+        error_kind: mock::ERROR_KINDS[0].to_owned(),
+        error_code: -32603,
+        block_interval: Duration::from_millis(100),
+        block_size: 10_000,
+        tls_cert: None,
+        tls_key: None,
+        admin: None,
+    }
+}
+
+fn start_mock_node(error_rate: f64) -> String {
+    mock::spawn(&mock_config(error_rate)).to_string()
+}
+
+fn load_config(node: &str, args: &[&str]) -> AppConfig {
+    let amount = AMOUNT.to_string();
+    let thread = THREAD.to_string();
+    let mut argv = vec![
+        "cita-bench",
+        "--node",
+        node,
+        "--amount",
+        &amount,
+        "--thread",
+        &thread,
+        "--warmup",
+        "0s",
+    ];
+    argv.extend_from_slice(args);
+    let matches = build_commandline().get_matches_from(argv);
+    AppConfig::from(&matches)
+}
+
+// Runs as `main` does, but without the output.
+fn run(config: AppConfig) -> GeneralSummary {
+    let mission = generate_mission(&config);
+    carry_out(config, mission).summarize()
+}
+
+// Each test writes its own files, since the tests run at the same time, and so may other runs.
+fn temp_file(name: &str, content: &str) -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let unique = format!(
+        "cita-bench-test-{}-{}-{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst),
+        name
+    );
+    let path = env::temp_dir().join(unique);
+    fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn query_categories() {
+    let node = start_mock_node(0.0);
+    let cases: Vec<(&str, Vec<&str>)> = vec![
+        ("peerCount", vec![]),
+        ("blockNumber", vec![]),
+        ("getMetaData", vec![]),
+        ("getVersion", vec![]),
+        // Blocks are polled over HTTP.
+        ("newBlockFilter", vec![]),
+        ("call", vec!["--contract", CONTRACT, "--function", "get()"]),
+    ];
+    for (category, extra) in cases {
+        let mut args = vec!["--category", category];
+        args.extend(extra);
+        let config = load_config(&node, &args);
+        let summary = run(config);
+        assert_eq!(summary.stat.success, AMOUNT * THREAD, "{}", category);
+        assert_eq!(summary.stat.failure, 0, "{}", category);
+        assert!(summary.confirm.is_none(), "{}", category);
+        assert!(summary.delivery.is_none(), "{}", category);
+    }
+}
+
+#[test]
+fn transaction_categories() {
+    let node = start_mock_node(0.0);
+    let bytecode = temp_file("transactions.hex", "0x6080604052");
+    let cases: Vec<(&str, Vec<&str>)> = vec![
+        ("sendRawTransaction", vec![]),
+        ("deployContract", vec!["--bytecode", &bytecode]),
+        (
+            "callContract",
+            vec![
+                "--contract",
+                CONTRACT,
+                "--function",
+                "set(uint256)",
+                "--arg",
+                "1",
+            ],
+        ),
+        ("transfer", vec!["--generate-keys", "2"]),
+    ];
+    for (category, extra) in cases {
+        let mut args = vec!["--category", category, "--confirm-timeout", "10"];
+        args.extend(extra);
+        let config = load_config(&node, &args);
+        let summary = run(config);
+        assert_eq!(summary.stat.success, AMOUNT * THREAD, "{}", category);
+        assert_eq!(summary.stat.failure, 0, "{}", category);
+        let confirm = summary.confirm.expect("transactions are tracked");
+        assert_eq!(confirm.committed, AMOUNT * THREAD, "{}", category);
+        assert_eq!(confirm.missing + confirm.unconfirmed, 0, "{}", category);
+        let block = summary.block.expect("blocks are observed");
+        assert_eq!(block.tx_total, AMOUNT * THREAD, "{}", category);
+        if category == "transfer" {
+            let balance = summary.balance.expect("balances are verified");
+            assert_eq!(balance.committed, AMOUNT * THREAD);
+            assert_eq!(balance.mismatched, 0);
+        } else {
+            assert!(summary.balance.is_none(), "{}", category);
+        }
+    }
+}
+
+#[test]
+fn permissions_are_granted() {
+    let mut c = mock_config(0.0);
+    c.admin = Some(ADMIN_KEY.parse::<PrivateKey>().unwrap().address());
+    let node = mock::spawn(&c).to_string();
+    let bytecode = temp_file("permissions.hex", "0x6080604052");
+    let args = [
+        "--category",
+        "deployContract",
+        "--bytecode",
+        &bytecode,
+        "--generate-keys",
+        "2",
+        "--confirm-timeout",
+        "10",
+    ];
+    // The generated senders have no permission yet.
+    let summary = run(load_config(&node, &args));
+    assert_eq!(summary.stat.success, 0);
+    assert_eq!(summary.stat.failure_kind["Rejected"], AMOUNT * THREAD);
+
+    let mut args = args.to_vec();
+    args.extend(&["--admin-key", ADMIN_KEY]);
+    let summary = run(load_config(&node, &args));
+    assert_eq!(summary.stat.success, AMOUNT * THREAD);
+    assert_eq!(summary.confirm.unwrap().committed, AMOUNT * THREAD);
+}
+
+#[test]
+fn sample_categories() {
+    let node = start_mock_node(0.0);
+    // Deploy contracts first, so the blocks, transactions and contracts are sampled.
+    let bytecode = temp_file("samples.hex", "0x6080604052");
+    let args = [
+        "--category",
+        "deployContract",
+        "--bytecode",
+        &bytecode,
+        "--confirm-timeout",
+        "10",
+    ];
+    let summary = run(load_config(&node, &args));
+    assert_eq!(summary.confirm.unwrap().committed, AMOUNT * THREAD);
+    for category in &[
+        "getBlockByNumber",
+        "getBlockByHash",
+        "getTransaction",
+        "getTransactionReceipt",
+        "getTransactionCount",
+        "getBalance",
+        "getCode",
+        "getAbi",
+        "getLogs",
+        "getTransactionProof",
+        "getBlockHeader",
+        "getStateProof",
+    ] {
+        let config = load_config(&node, &["--category", category]);
+        let summary = run(config);
+        assert_eq!(summary.stat.success, AMOUNT * THREAD, "{}", category);
+        assert_eq!(summary.stat.failure, 0, "{}", category);
+    }
+}
+
+#[test]
+fn mixed_categories() {
+    let node = start_mock_node(0.0);
+    let args = [
+        "--mix",
+        "blockNumber=1,sendRawTransaction=1",
+        "--confirm-timeout",
+        "10",
+    ];
+    let summary = run(load_config(&node, &args));
+    assert_eq!(summary.stat.success, AMOUNT * THREAD);
+    assert_eq!(summary.stat.failure, 0);
+    let queries = summary.stat.category["blockNumber"].success;
+    let txs = summary.stat.category["sendRawTransaction"].success;
+    assert_eq!(queries + txs, AMOUNT * THREAD);
+    let confirm = summary.confirm.expect("transactions are tracked");
+    assert_eq!(confirm.committed, txs);
+}
+
+#[test]
+fn replayed_transactions() {
+    let node = start_mock_node(0.0);
+    let path = temp_file("replay.txt", "");
+    let argv = vec![
+        "cita-bench",
+        "generate",
+        "--node",
+        &node,
+        "--amount",
+        "10",
+        "--category",
+        "transfer",
+        "--generate-keys",
+        "2",
+        "--output-file",
+        &path,
+    ];
+    let matches = build_commandline().get_matches_from(argv);
+    let config = AppConfig::from(matches.subcommand_matches("generate").unwrap());
+    replay::generate(&config).unwrap();
+
+    let thread = THREAD.to_string();
+    let argv = vec![
+        "cita-bench",
+        "--node",
+        &node,
+        "--thread",
+        &thread,
+        "--warmup",
+        "0s",
+        "--replay",
+        &path,
+        "--confirm-timeout",
+        "10",
+    ];
+    let summary = run(AppConfig::from(
+        &build_commandline().get_matches_from(argv.clone()),
+    ));
+    assert_eq!(summary.stat.success, 10);
+    assert_eq!(summary.stat.failure, 0);
+    assert_eq!(summary.confirm.unwrap().committed, 10);
+    assert_eq!(summary.balance.unwrap().mismatched, 0);
+
+    // The transactions are committed already, so they are all rejected.
+    let summary = run(AppConfig::from(&build_commandline().get_matches_from(argv)));
+    assert_eq!(summary.stat.success, 0);
+    assert_eq!(summary.stat.failure_kind["Rejected"], 10);
+}
+
+#[test]
+fn batches_and_async_engine() {
+    let node = start_mock_node(0.0);
+    for args in &[
+        vec!["--category", "blockNumber", "--batch", "5"],
+        vec!["--category", "blockNumber", "--engine", "async"],
+        vec![
+            "--category",
+            "peerCount",
+            "--engine",
+            "async",
+            "--batch",
+            "4",
+        ],
+    ] {
+        let config = load_config(&node, args);
+        let summary = run(config);
+        assert_eq!(summary.stat.success, AMOUNT * THREAD, "{:?}", args);
+        assert_eq!(summary.stat.failure, 0, "{:?}", args);
+    }
+}
+
+#[test]
+fn websocket_transport() {
+    let node = start_mock_node(0.0);
+    for args in &[
+        vec!["--category", "blockNumber"],
+        vec!["--category", "getVersion", "--batch", "4"],
+        vec!["--category", "peerCount", "--engine", "async"],
+    ] {
+        let mut args = args.clone();
+        args.extend(&["--protocol", "ws"]);
+        let summary = run(load_config(&node, &args));
+        assert_eq!(summary.stat.success, AMOUNT * THREAD, "{:?}", args);
+        assert_eq!(summary.stat.failure, 0, "{:?}", args);
+    }
+}
+
+#[test]
+fn blocks_are_pushed_over_websocket() {
+    let node = start_mock_node(0.0);
+    let args = ["--category", "newBlockFilter", "--protocol", "ws"];
+    let summary = run(load_config(&node, &args));
+    assert_eq!(summary.stat.success, AMOUNT * THREAD);
+    assert_eq!(summary.stat.failure, 0);
+    let delivery = summary.delivery.expect("pushed blocks are delivered");
+    assert!(delivery.delivered > 0);
+}
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn tls_transports() {
+    let mut c = mock_config(0.0);
+    c.tls_cert = Some(fixture("localhost.pem"));
+    c.tls_key = Some(fixture("localhost.key"));
+    let node = mock::spawn(&c).to_string();
+    let ca = fixture("ca.pem");
+    for args in &[
+        vec!["--protocol", "https"],
+        vec!["--protocol", "https", "--engine", "async", "--batch", "4"],
+        vec!["--protocol", "wss"],
+        vec!["--protocol", "wss", "--engine", "async", "--batch", "4"],
+    ] {
+        let mut args = args.clone();
+        args.extend(&["--category", "blockNumber", "--tls-ca", ca.as_str()]);
+        args.extend(&["--tls-sni", "localhost"]);
+        let summary = run(load_config(&node, &args));
+        assert_eq!(summary.stat.success, AMOUNT * THREAD, "{:?}", args);
+        assert_eq!(summary.stat.failure, 0, "{:?}", args);
+    }
+}
+
+// Ramps from 10 to 31 req/s in the first second, for 20.5 slots, then holds at 40 req/s.
+fn load_scenario_config(node: &str, name: &str) -> AppConfig {
+    let path = temp_file(
+        name,
+        "[[phase]]\nname = \"ramp\"\nduration = \"1s\"\nrate = 10\nrate-to = 31\nthread = 1\n\n\
+         [[phase]]\nname = \"hold\"\nduration = \"1s\"\nrate = 40\n",
+    );
+    let thread = THREAD.to_string();
+    let argv = vec![
+        "cita-bench",
+        "--node",
+        node,
+        "--thread",
+        &thread,
+        "--scenario",
+        &path,
+        "--category",
+        "blockNumber",
+    ];
+    let matches = build_commandline().get_matches_from(argv);
+    AppConfig::from(&matches)
+}
+
+#[test]
+fn scenario_is_split_by_phases() {
+    let node = start_mock_node(0.0);
+    let config = load_scenario_config(&node, "scenario.toml");
+    assert_eq!(config.duration, Some(Duration::from_secs(2)));
+    let summary = run(config);
+    assert_eq!(summary.stat.failure, 0);
+    assert_eq!(summary.phase.len(), 2);
+    assert_eq!(summary.phase[0].name, "ramp");
+    assert_eq!(summary.phase[0].thread, Some(1));
+    assert_eq!(summary.phase[1].name, "hold");
+    assert_eq!(summary.phase[1].thread, None);
+    // Each slot of the schedule is sent once, in the phase which it is intended for.
+    assert_eq!(summary.phase[0].stat.success, 21);
+    assert_eq!(summary.phase[1].stat.success, 40);
+    assert_eq!(summary.stat.success, 21 + 40);
+}
+
+#[test]
+fn scenario_schedules_slots() {
+    let config = load_scenario_config("127.0.0.1:1", "schedule.toml");
+    let schedule = Schedule::phased(Arc::new(config.scenario.unwrap()));
+    let start = Instant::now();
+    let mut offsets = Vec::new();
+    loop {
+        let offset = schedule.next_slots(start, 1).unwrap() - start;
+        if offset >= Duration::from_secs(2) {
+            break;
+        }
+        offsets.push(offset);
+    }
+    let (ramp, hold): (Vec<_>, Vec<_>) = offsets
+        .into_iter()
+        .partition(|offset| *offset < Duration::from_secs(1));
+    assert_eq!(ramp.len(), 21);
+    assert_eq!(hold.len(), 40);
+    // The rate goes up in the ramp.
+    assert!(ramp[1] - ramp[0] > ramp[20] - ramp[19]);
+    // The hold starts from its own beginning, and each slot takes 25ms.
+    assert_eq!(hold[0], Duration::from_secs(1));
+    for pair in hold.windows(2) {
+        assert_eq!(pair[1] - pair[0], Duration::from_millis(25));
+    }
+    // Slots are taken in batches as well.
+    let schedule = Schedule::phased(Arc::new(
+        load_scenario_config("127.0.0.1:1", "batches.toml")
+            .scenario
+            .unwrap(),
+    ));
+    assert_eq!(schedule.next_slots(start, 21), Some(start));
+    assert_eq!(
+        schedule.next_slots(start, 1),
+        Some(start + Duration::from_secs(1))
+    );
+}
+
+#[test]
+fn errors_are_classified() {
+    let cases = vec![
+        ("blockNumber", -32601, "Rpc(-32601)"),
+        ("sendRawTransaction", -32006, "Rejected"),
+    ];
+    for (category, code, kind) in cases {
+        let mut c = mock_config(1.0);
+        c.error_methods = vec![category.to_owned()];
+        c.error_code = code;
+        let node = mock::spawn(&c).to_string();
+        let config = load_config(&node, &["--category", category]);
+        let summary = run(config);
+        assert_eq!(summary.stat.success, 0, "{}", category);
+        assert_eq!(summary.stat.failure, AMOUNT * THREAD, "{}", category);
+        assert_eq!(
+            summary.stat.failure_kind[kind],
+            AMOUNT * THREAD,
+            "{}",
+            category
+        );
+    }
+
+    let node = start_mock_node(0.5);
+    let config = load_config(&node, &["--category", "blockNumber"]);
+    let summary = run(config);
+    assert_eq!(summary.stat.success + summary.stat.failure, AMOUNT * THREAD);
+    assert!(summary.stat.failure > 0);
+    assert_eq!(summary.stat.failure_kind.len(), 1);
+    assert_eq!(
+        summary.stat.failure_kind["Rpc(-32603)"],
+        summary.stat.failure
+    );
+
+    let config = load_config("127.0.0.1:1", &["--category", "blockNumber"]);
+    let summary = run(config);
+    assert_eq!(summary.stat.failure, AMOUNT * THREAD);
+    assert_eq!(summary.stat.failure_kind["Transport"], AMOUNT * THREAD);
+}
+
+#[test]
+fn malformed_responses_fail_to_decode() {
+    let mut c = mock_config(0.5);
+    c.error_methods = vec!["blockNumber".to_owned()];
+    c.error_kind = "malformed".to_owned();
+    let node = mock::spawn(&c).to_string();
+    let config = load_config(&node, &["--category", "blockNumber"]);
+    let summary = run(config);
+    assert_eq!(summary.stat.success + summary.stat.failure, AMOUNT * THREAD);
+    assert!(summary.stat.failure > 0);
+    assert_eq!(summary.stat.failure_kind.len(), 1);
+    assert_eq!(summary.stat.failure_kind["Decode"], summary.stat.failure);
+}
+
+#[test]
+fn stalled_requests_time_out() {
+    // Accepts connections but never answers, not even the TLS handshake.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let node = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let mut stalled = Vec::new();
+        for stream in listener.incoming() {
+            stalled.push(stream);
+        }
+    });
+    let args = [
+        "--category",
+        "peerCount",
+        "--protocol",
+        "https",
+        "--tls-insecure",
+        "--tls-sni",
+        "localhost",
+        "--tls-timeout",
+        "100ms",
+    ];
+    let summary = run(load_config(&node, &args));
+    assert_eq!(summary.stat.failure, AMOUNT * THREAD);
+    assert_eq!(summary.stat.failure_kind["Timeout"], AMOUNT * THREAD);
+}
+
+// Queries until a few of them succeed, then terminates the mission.
+struct Terminating {
+    // Set once the mission is generated.
+    terminate: Arc<Mutex<Option<Arc<RwLock<bool>>>>>,
+    done: AtomicUsize,
+}
+
+impl Workload for Terminating {
+    fn categories(&self) -> Vec<&'static str> {
+        vec!["blockNumber"]
+    }
+
+    fn init<'a>(&'a self, _node: &Node, _soldier_id: usize) -> Box<Soldier + 'a> {
+        Box::new(TerminatingSoldier { workload: self })
+    }
+}
+
+struct TerminatingSoldier<'a> {
+    workload: &'a Terminating,
+}
+
+impl<'a> Soldier for TerminatingSoldier<'a> {
+    fn next(&mut self, _web3: &Web3Client) -> Option<Prepared> {
+        Some(Prepared::new(BlockNumberParams::new(), |_, _| Ok(())))
+    }
+
+    fn done(&mut self, step: &StepOutcome) {
+        if step.error_kind().is_none() && self.workload.done.fetch_add(1, Ordering::SeqCst) == 2 {
+            let terminate = self.workload.terminate.lock().unwrap();
+            *terminate.as_ref().unwrap().write().unwrap() = true;
+        }
+    }
+}
+
+#[test]
+fn terminate_stops_mission() {
+    let node = start_mock_node(0.0);
+    let config = load_config(&node, &["--duration", "1h"]);
+    let terminate = Arc::new(Mutex::new(None));
+    let workload = Terminating {
+        terminate: terminate.clone(),
+        done: AtomicUsize::new(0),
+    };
+    let mission = generate_workload_mission(&config, Box::new(workload));
+    *terminate.lock().unwrap() = Some(mission.data.terminate.clone());
+    let summary = generate_report(config, mission).summarize();
+    // Each soldier may take one more step before it sees the termination.
+    assert!(summary.stat.success >= 3);
+    assert!(summary.stat.success <= 3 + THREAD);
+    assert_eq!(summary.stat.failure, 0);
+}
+
+// Sends the height query for a few times from the fetched height, then stops.
+struct FewQueries {
+    height: Option<u64>,
+    done: Arc<AtomicUsize>,
+}
+
+struct FewQueriesSoldier<'a> {
+    workload: &'a FewQueries,
+    left: usize,
+}
+
+impl Workload for FewQueries {
+    fn categories(&self) -> Vec<&'static str> {
+        vec!["fewQueries"]
+    }
+
+    fn prepare(&mut self, web3: &Web3Client) -> Result<(), String> {
+        let height = fetch_height(web3).map_err(|err| err.to_string())?;
+        self.height = Some(height);
+        Ok(())
+    }
+
+    fn init<'a>(&'a self, _node: &Node, _soldier_id: usize) -> Box<Soldier + 'a> {
+        Box::new(FewQueriesSoldier {
+            workload: self,
+            left: 5,
+        })
+    }
+}
+
+impl<'a> Soldier for FewQueriesSoldier<'a> {
+    fn next(&mut self, _web3: &Web3Client) -> Option<Prepared> {
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        assert!(self.workload.height.is_some());
+        let call = Prepared::new(BlockNumberParams::new(), |_, _| Ok(()));
+        Some(call.of("fewQueries"))
+    }
+
+    fn done(&mut self, step: &StepOutcome) {
+        assert_eq!(step.category, "fewQueries");
+        if step.error_kind().is_none() {
+            self.workload.done.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[test]
+fn custom_workload() {
+    let node = start_mock_node(0.0);
+    let config = load_config(&node, &[]);
+    let done = Arc::new(AtomicUsize::new(0));
+    let workload = FewQueries {
+        height: None,
+        done: done.clone(),
+    };
+    let mission = generate_workload_mission(&config, Box::new(workload));
+    let summary = carry_out(config, mission).summarize();
+    assert_eq!(summary.title, "fewQueries");
+    assert_eq!(summary.stat.success, 5 * THREAD);
+    assert_eq!(summary.stat.failure, 0);
+    assert_eq!(done.load(Ordering::SeqCst), 5 * THREAD);
+}
+
+// Sends transactions which are signed by a key of its own.
+struct OwnTransactions {
+    chain_id: U256,
+    // Fetched before the soldiers start, so no call is waited for in the steps.
+    height: u64,
+    tx_params: TxParams,
+}
+
+impl Workload for OwnTransactions {
+    fn categories(&self) -> Vec<&'static str> {
+        vec!["ownTransactions"]
+    }
+
+    fn sends_transactions(&self) -> bool {
+        true
+    }
+
+    fn prepare(&mut self, web3: &Web3Client) -> Result<(), String> {
+        self.chain_id = fetch_chain_id(web3).map_err(|err| err.to_string())?;
+        self.height = fetch_height(web3).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    fn init<'a>(&'a self, _node: &Node, _soldier_id: usize) -> Box<Soldier + 'a> {
+        Box::new(OwnTransactionsSoldier {
+            workload: self,
+            key: PrivateKey::generate(),
+        })
+    }
+}
+
+struct OwnTransactionsSoldier<'a> {
+    workload: &'a OwnTransactions,
+    key: PrivateKey,
+}
+
+impl<'a> Soldier for OwnTransactionsSoldier<'a> {
+    fn next(&mut self, _web3: &Web3Client) -> Option<Prepared> {
+        let (tx, _) = sign_transaction(
+            &self.key,
+            self.workload.chain_id,
+            self.workload.height + 50,
+            &self.workload.tx_params,
+        );
+        let param = SendRawTransactionParams::new(tx.bytes.into());
+        let call = Prepared::with_hash(param, |resp: TxResponse, _| Ok(Some(resp.hash)));
+        Some(call.of("ownTransactions"))
+    }
+}
+
+#[test]
+fn custom_transactions_are_tracked() {
+    let node = start_mock_node(0.0);
+    let config = load_config(&node, &["--confirm-timeout", "10"]);
+    let workload = OwnTransactions {
+        chain_id: U256::zero(),
+        height: 0,
+        tx_params: TxParams::new("sendRawTransaction", &config),
+    };
+    let mission = generate_workload_mission(&config, Box::new(workload));
+    let summary = carry_out(config, mission).summarize();
+    assert_eq!(summary.title, "ownTransactions");
+    assert_eq!(summary.stat.success, AMOUNT * THREAD);
+    let confirm = summary.confirm.expect("transactions are tracked");
+    assert_eq!(confirm.committed, AMOUNT * THREAD);
+}
+
+#[test]
+fn signed_transactions_are_verified() {
+    let config = load_config("127.0.0.1:1", &["--category", "sendRawTransaction"]);
+    let tx_params = TxParams::new("sendRawTransaction", &config);
+    let key = PrivateKey::generate();
+    let chain_id = U256::from(1);
+    let (tx, transfer) = sign_transaction(&key, chain_id, 100, &tx_params);
+    assert!(transfer.is_none());
+    assert_eq!(tx.valid_until_block, 100);
+    let utx = UnverifiedTransaction::try_from(&tx.bytes).unwrap();
+    assert_eq!(utx.get_transaction().get_valid_until_block(), 100);
+    assert_eq!(utx.get_transaction().get_quota(), config.quota);
+    // Recovers the signer by the crypto feature which is enabled.
+    let signed = SignedTransaction::create(&utx).unwrap();
+    assert_eq!(signed.get_signer(), &key.keypair().pubkey().to_vec()[..]);
+}